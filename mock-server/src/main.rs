@@ -0,0 +1,170 @@
+//! Локальный сервер с контрактом LunkvayAPI: `/api/v1/*` и `/ws?roomId=` для событий комнаты.
+//! Маршруты, состояние и демо-данные общие с офлайн-режимом браузера (`src/api/mock`),
+//! поэтому модули подключаются из основного крейта по пути.
+
+#[allow(dead_code)]
+#[path = "../../src/models/mod.rs"]
+mod models;
+
+#[path = "../../src/api/mock/backend.rs"]
+mod backend;
+
+#[path = "../../src/api/mock/seed.rs"]
+mod seed;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+    routing::{any, get, post},
+};
+use backend::{MockBackend, MockEvent, MockRequest};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tower_http::cors::CorsLayer;
+use uuid::Uuid;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:5080";
+const API_PREFIX: &str = "/api/v1";
+
+#[derive(Clone)]
+struct AppState {
+    backend: Arc<Mutex<MockBackend>>,
+    events: broadcast::Sender<MockEvent>,
+}
+
+impl AppState {
+    fn backend(&self) -> std::sync::MutexGuard<'_, MockBackend> {
+        self.backend.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Как и у LunkvayAPI, одно соединение получает события одной комнаты из `roomId`.
+#[derive(Deserialize)]
+struct WsQuery {
+    #[serde(rename = "roomId")]
+    room_id: Uuid,
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = std::env::var("LUNKVAY_MOCK_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let (events, _) = broadcast::channel(256);
+    let state = AppState {
+        backend: Arc::new(Mutex::new(MockBackend::seeded())),
+        events,
+    };
+
+    let app = Router::new()
+        .route(&format!("{API_PREFIX}/{{*path}}"), any(api))
+        .route("/ws", get(ws))
+        .route("/__mock/reset", post(reset))
+        .layer(CorsLayer::permissive())
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|e| panic!("Не удалось занять адрес {addr}: {e}"));
+    println!("Мок-сервер LunkvayAPI: http://{addr}{API_PREFIX}, ws://{addr}/ws");
+    println!("Вход: ryan.gosling@gmail.com / realhero");
+
+    axum::serve(listener, app)
+        .await
+        .expect("Сервер остановлен с ошибкой");
+}
+
+async fn api(
+    State(state): State<AppState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path = uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_default()
+        .trim_start_matches(API_PREFIX);
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Файлы (аватары, картинки чатов) бэкенд не хранит, ему нужен только JSON.
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let body = is_json.then(|| String::from_utf8_lossy(&body).into_owned());
+
+    let (response, events) = {
+        let mut backend = state.backend();
+        let response = backend.handle(MockRequest {
+            method: method.as_str(),
+            path,
+            token,
+            body: body.as_deref(),
+        });
+        (response, backend.take_events())
+    };
+    for event in events {
+        // Ошибка означает лишь, что сейчас никто не подключён к сокету.
+        let _ = state.events.send(event);
+    }
+
+    let content_type = if response.body.starts_with(['{', '[']) {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (
+        status,
+        [(header::CONTENT_TYPE, content_type)],
+        response.body,
+    )
+        .into_response()
+}
+
+/// Сбрасывает состояние к демо-данным — удобно перед каждым сценарием.
+async fn reset(State(state): State<AppState>) -> StatusCode {
+    *state.backend() = MockBackend::seeded();
+    StatusCode::NO_CONTENT
+}
+
+async fn ws(
+    State(state): State<AppState>,
+    Query(query): Query<WsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let events = state.events.subscribe();
+    upgrade.on_upgrade(move |socket| room(socket, query.room_id, events))
+}
+
+async fn room(mut socket: WebSocket, room_id: Uuid, mut events: broadcast::Receiver<MockEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if event.room_id == room_id => {
+                    let Ok(text) = serde_json::to_string(&event.message) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
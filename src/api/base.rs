@@ -1,8 +1,12 @@
+use super::cache;
 use super::error::ApiError;
-use crate::utils::{API_BASE_URL, clear_token, local_storage};
-use reqwasm::http::{Method, Request, Response};
+use super::retry::{RetryPolicy, sleep_unless_aborted};
+use super::transport::{HttpRequest, HttpResponse, RequestBody, transport};
+use crate::auth::auth_state;
+use reqwasm::http::Method;
 use serde::{Serialize, de::DeserializeOwned};
-use web_sys::FormData;
+use std::time::Duration;
+use web_sys::{AbortSignal, FormData};
 
 pub struct ApiClient;
 
@@ -37,6 +41,11 @@ pub struct RequestBuilder<'a, B: Serialize> {
     path: &'a str,
     body: Option<&'a B>,
     auth: bool,
+    retry: RetryPolicy,
+    idempotent: bool,
+    abort: Option<AbortSignal>,
+    cache_ttl: Option<Duration>,
+    invalidates: Vec<String>,
 }
 
 impl<'a, B: Serialize> RequestBuilder<'a, B> {
@@ -46,6 +55,11 @@ impl<'a, B: Serialize> RequestBuilder<'a, B> {
             path,
             body,
             auth: false,
+            retry: RetryPolicy::none(),
+            idempotent: matches!(method, Method::GET),
+            abort: None,
+            cache_ttl: None,
+            invalidates: Vec::new(),
         }
     }
 
@@ -54,73 +68,98 @@ impl<'a, B: Serialize> RequestBuilder<'a, B> {
         self
     }
 
-    async fn send_base(self) -> Result<Response, ApiError> {
-        let url = format!("{}{}", API_BASE_URL, self.path);
-        let mut request_builder = Request::new(&url).method(self.method.clone());
+    /// Повторять запрос при сетевых сбоях, таймаутах и 408/429/502/503/504.
+    /// Без `idempotent()` повторяются только GET.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
 
-        if self.auth {
-            let storage =
-                local_storage().ok_or(ApiError::Network("localStorage не доступен".to_string()))?;
-            let token = storage
-                .get_item("token")
-                .map_err(|_| ApiError::Network("Не удалось получить токен".to_string()))?
-                .ok_or(ApiError::Unauthorized)?;
-            request_builder = request_builder.header("Authorization", &format!("Bearer {}", token));
-        }
+    /// Отмена запроса вместе с повторами, обычно от `cancel::LatestRequest`.
+    pub fn abort_signal(mut self, signal: Option<AbortSignal>) -> Self {
+        self.abort = signal;
+        self
+    }
 
-        if let Some(body_content) = self.body {
-            let request_body = serde_json::to_string(body_content)
-                .map_err(|e| ApiError::Parsing(e.to_string()))?;
-            request_builder = request_builder
-                .header("Content-Type", "application/json")
-                .body(request_body);
-        }
+    /// Разрешает повторы для DELETE/PATCH, которые безопасно отправить дважды.
+    pub fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
 
-        request_builder
-            .send()
-            .await
-            .map_err(|e| ApiError::Network(e.to_string()))
+    /// Кэширует ответ GET на `ttl` и объединяет одинаковые одновременные запросы.
+    pub fn cached(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
     }
 
-    pub async fn send_json<T: DeserializeOwned>(self) -> Result<T, ApiError> {
-        let response = self.send_base().await?;
-        if response.status() == 401 {
-            clear_token();
-            return Err(ApiError::Unauthorized);
-        }
-        if !response.ok() {
-            return Err(ApiError::from_response(response).await);
+    /// После запроса сбрасывает кэш с путями, начинающимися на `prefix`.
+    pub fn invalidates(mut self, prefix: impl Into<String>) -> Self {
+        self.invalidates.push(prefix.into());
+        self
+    }
+
+    async fn send_base(self) -> Result<HttpResponse, ApiError> {
+        let body = match self.body {
+            Some(body_content) => RequestBody::Json(
+                serde_json::to_string(body_content)
+                    .map_err(|e| ApiError::Parsing(e.to_string()))?,
+            ),
+            None => RequestBody::Empty,
+        };
+
+        let policy = if self.idempotent {
+            self.retry
+        } else {
+            RetryPolicy {
+                max_attempts: 1,
+                ..self.retry
+            }
+        };
+
+        let request = HttpRequest {
+            method: self.method,
+            path: self.path.to_string(),
+            token: auth_token(self.auth)?,
+            body,
+            timeout: Some(policy.timeout),
+            abort: self.abort,
+        };
+
+        let result = match self.cache_ttl {
+            Some(ttl) if matches!(self.method, Method::GET) => {
+                let shared = request.clone();
+                let result = cache::fetch(self.path, request.token.as_deref(), ttl, move || {
+                    Box::pin(send_checked(shared, policy))
+                })
+                .await;
+                match result {
+                    // Общий запрос отменил другой подписчик, а этот ещё ждёт ответа.
+                    Err(ApiError::Aborted) if !request.is_aborted() => {
+                        send_checked(request, policy).await
+                    }
+                    result => result,
+                }
+            }
+            _ => send_checked(request, policy).await,
+        };
+
+        for prefix in &self.invalidates {
+            cache::invalidate(prefix);
         }
-        response
-            .json::<T>()
-            .await
-            .map_err(|e| ApiError::Parsing(e.to_string()))
+        result
+    }
+
+    pub async fn send_json<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        self.send_base().await?.json::<T>()
     }
 
     pub async fn send_text(self) -> Result<String, ApiError> {
-        let response = self.send_base().await?;
-        if response.status() == 401 {
-            clear_token();
-            return Err(ApiError::Unauthorized);
-        }
-        if !response.ok() {
-            return Err(ApiError::from_response(response).await);
-        }
-        response
-            .text()
-            .await
-            .map_err(|e| ApiError::Parsing(e.to_string()))
+        Ok(self.send_base().await?.text().to_string())
     }
 
     pub async fn send_empty(self) -> Result<(), ApiError> {
-        let response = self.send_base().await?;
-        if response.status() == 401 {
-            clear_token();
-            return Err(ApiError::Unauthorized);
-        }
-        if !response.ok() {
-            return Err(ApiError::from_response(response).await);
-        }
+        self.send_base().await?;
         Ok(())
     }
 }
@@ -130,6 +169,7 @@ pub struct RequestBuilderFormData<'a> {
     path: &'a str,
     body: FormData,
     auth: bool,
+    timeout: Duration,
 }
 
 impl<'a> RequestBuilderFormData<'a> {
@@ -139,6 +179,7 @@ impl<'a> RequestBuilderFormData<'a> {
             path,
             body,
             auth: false,
+            timeout: RetryPolicy::none().timeout,
         }
     }
 
@@ -147,25 +188,75 @@ impl<'a> RequestBuilderFormData<'a> {
         self
     }
 
-    pub async fn send_base(self) -> Result<Response, ApiError> {
-        let url = format!("{}{}", API_BASE_URL, self.path);
-        let mut request_builder = Request::new(&url).method(self.method.clone());
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 
-        if self.auth {
-            let storage =
-                local_storage().ok_or(ApiError::Network("localStorage не доступен".to_string()))?;
-            let token = storage
-                .get_item("token")
-                .map_err(|_| ApiError::Network("Не удалось получить токен".to_string()))?
-                .ok_or(ApiError::Unauthorized)?;
-            request_builder = request_builder.header("Authorization", &format!("Bearer {}", token));
-        }
+    pub async fn send_text(self) -> Result<String, ApiError> {
+        let response = send_checked(
+            HttpRequest {
+                method: self.method,
+                path: self.path.to_string(),
+                token: auth_token(self.auth)?,
+                body: RequestBody::FormData(self.body),
+                timeout: Some(self.timeout),
+                abort: None,
+            },
+            RetryPolicy::none(),
+        )
+        .await?;
+        Ok(response.text().to_string())
+    }
+}
+
+fn auth_token(auth: bool) -> Result<Option<String>, ApiError> {
+    if !auth {
+        return Ok(None);
+    }
+    let session = auth_state()
+        .session_untracked()
+        .ok_or(ApiError::Unauthorized)?;
+    // Истёкший токен сервер всё равно отклонит, а запрос мог быть изменяющим.
+    if session.is_expired() {
+        auth_state().expire();
+        return Err(ApiError::Unauthorized);
+    }
+    Ok(Some(session.token))
+}
 
-        request_builder = request_builder.body(self.body);
+async fn send_checked(request: HttpRequest, policy: RetryPolicy) -> Result<HttpResponse, ApiError> {
+    let mut attempt = 0;
+    let response = loop {
+        if request.is_aborted() {
+            return Err(ApiError::Aborted);
+        }
+        let is_last = attempt + 1 >= policy.max_attempts;
+        let delay = match transport().send(request.clone()).await {
+            Ok(response) => match policy.delay_after(&response, attempt) {
+                Some(delay) if !is_last => delay,
+                _ => break response,
+            },
+            Err(ApiError::Network(_) | ApiError::Timeout) if !is_last => policy.backoff(attempt),
+            Err(e) => return Err(e),
+        };
+        // Отменённый запрос не должен досиживать паузу до конца
+        if !sleep_unless_aborted(delay, request.abort.as_ref()).await {
+            return Err(ApiError::Aborted);
+        }
+        attempt += 1;
+    };
 
-        request_builder
-            .send()
-            .await
-            .map_err(|e| ApiError::Network(e.to_string()))
+    // Без токена 401 — это ответ на сам запрос (например, неверный пароль),
+    // а не признак того, что сессия кончилась.
+    if response.status() == 401 {
+        if request.token.is_some() {
+            auth_state().expire();
+        }
+        return Err(ApiError::Unauthorized);
+    }
+    if !response.ok() {
+        return Err(ApiError::from_response(response));
     }
+    Ok(response)
 }
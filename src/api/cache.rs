@@ -0,0 +1,112 @@
+use super::{error::ApiError, transport::HttpResponse};
+use futures::future::{FutureExt, LocalBoxFuture, Shared};
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+
+type SharedResponse = Shared<LocalBoxFuture<'static, Result<HttpResponse, ApiError>>>;
+
+enum State {
+    Pending(SharedResponse),
+    Ready {
+        response: HttpResponse,
+        stored_at: f64,
+    },
+}
+
+/// Запись кэша по пути запроса. Токен хранится рядом, чтобы после смены
+/// пользователя не отдать чужие данные.
+struct Entry {
+    token: Option<String>,
+    state: State,
+}
+
+enum Lookup {
+    Hit(HttpResponse),
+    Wait(SharedResponse),
+}
+
+thread_local! {
+    static ENTRIES: RefCell<HashMap<String, Entry>> = RefCell::new(HashMap::new());
+}
+
+fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Отдаёт свежий ответ из памяти, присоединяется к уже идущему запросу с тем же
+/// путём или запускает `request` и запоминает успешный ответ на `ttl`.
+pub async fn fetch(
+    path: &str,
+    token: Option<&str>,
+    ttl: Duration,
+    request: impl FnOnce() -> LocalBoxFuture<'static, Result<HttpResponse, ApiError>>,
+) -> Result<HttpResponse, ApiError> {
+    let lookup = ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        let cached = entries
+            .get(path)
+            .filter(|entry| entry.token.as_deref() == token);
+        match cached.map(|entry| &entry.state) {
+            Some(State::Ready {
+                response,
+                stored_at,
+            }) if now() - stored_at < ttl.as_millis() as f64 => Lookup::Hit(response.clone()),
+            Some(State::Pending(shared)) => Lookup::Wait(shared.clone()),
+            _ => {
+                let shared = request().shared();
+                entries.insert(
+                    path.to_string(),
+                    Entry {
+                        token: token.map(str::to_string),
+                        state: State::Pending(shared.clone()),
+                    },
+                );
+                Lookup::Wait(shared)
+            }
+        }
+    });
+    let pending = match lookup {
+        Lookup::Hit(response) => return Ok(response),
+        Lookup::Wait(pending) => pending,
+    };
+
+    let result = pending.clone().await;
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        // Запись могли сбросить или заменить, пока шёл запрос: тогда ответ устарел.
+        let is_ours = matches!(
+            entries.get(path).map(|entry| &entry.state),
+            Some(State::Pending(current)) if current.ptr_eq(&pending)
+        );
+        if !is_ours {
+            return;
+        }
+        match &result {
+            Ok(response) => {
+                if let Some(entry) = entries.get_mut(path) {
+                    entry.state = State::Ready {
+                        response: response.clone(),
+                        stored_at: now(),
+                    };
+                }
+            }
+            Err(_) => {
+                entries.remove(path);
+            }
+        }
+    });
+    result
+}
+
+/// Сбрасывает записи, путь которых начинается с `prefix`, например
+/// `/chats/members/{id}` после изменения участников чата.
+pub fn invalidate(prefix: &str) {
+    ENTRIES.with(|entries| {
+        entries
+            .borrow_mut()
+            .retain(|path, _| !path.starts_with(prefix))
+    });
+}
+
+pub fn clear() {
+    ENTRIES.with(|entries| entries.borrow_mut().clear());
+}
@@ -0,0 +1,48 @@
+use leptos::prelude::*;
+use web_sys::{AbortController, AbortSignal};
+
+/// Держит в полёте только последний запрос ресурса: каждый новый вызов `next()`
+/// отменяет предыдущий, а при размонтировании компонента отменяется и текущий.
+/// Так ответ для старого чата или профиля не попадёт в новый экран.
+#[derive(Clone, Copy)]
+pub struct LatestRequest {
+    controller: StoredValue<Option<AbortController>, LocalStorage>,
+}
+
+impl LatestRequest {
+    pub fn new() -> Self {
+        let latest = Self {
+            controller: StoredValue::new_local(None),
+        };
+        // Очистка владельца выполняется раньше, чем освобождается StoredValue.
+        on_cleanup(move || latest.abort());
+        latest
+    }
+
+    /// Отменяет предыдущий запрос и возвращает сигнал для следующего.
+    pub fn next(&self) -> Option<AbortSignal> {
+        let controller = AbortController::new().ok();
+        let signal = controller.as_ref().map(|c| c.signal());
+        let previous = self
+            .controller
+            .try_update_value(|current| std::mem::replace(current, controller))
+            .flatten();
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        signal
+    }
+
+    fn abort(&self) {
+        let current = self.controller.try_update_value(Option::take).flatten();
+        if let Some(controller) = current {
+            controller.abort();
+        }
+    }
+}
+
+impl Default for LatestRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
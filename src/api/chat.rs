@@ -1,7 +1,16 @@
-use super::{base::ApiClient, error::ApiError};
+use super::{base::ApiClient, cache, error::ApiError, retry::RetryPolicy};
 use crate::models::chat::Chat;
+use crate::sync::{SyncEvent, broadcast};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
+use web_sys::AbortSignal;
+
+/// Список чатов запрашивают навигация для счётчиков, страница чатов и пересылка.
+const CHATS_TTL: Duration = Duration::from_secs(30);
+
+/// Префикс покрывает и участников чатов: после удаления чата они тоже не нужны.
+const CHATS_PATH: &str = "/chats";
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,20 +25,35 @@ pub struct UpdateChatRequest {
     pub new_name: String,
 }
 
-pub async fn get_all_chats() -> Result<Vec<Chat>, ApiError> {
-    ApiClient::get("/chats").authenticated().send_json().await
+pub async fn get_all_chats(abort: Option<AbortSignal>) -> Result<Vec<Chat>, ApiError> {
+    ApiClient::get(CHATS_PATH)
+        .authenticated()
+        .retry(RetryPolicy::default())
+        .cached(CHATS_TTL)
+        .abort_signal(abort)
+        .send_json()
+        .await
+}
+
+/// Сбрасывает кэш списка, например когда в чате появилось новое последнее сообщение.
+pub fn invalidate_chats() {
+    cache::invalidate(CHATS_PATH);
 }
 
 pub async fn create_group_chat(request: CreateGroupChatRequest) -> Result<Chat, ApiError> {
-    ApiClient::post("/chats/group", &request)
+    let chat = ApiClient::post("/chats/group", &request)
         .authenticated()
+        .invalidates(CHATS_PATH)
         .send_json()
-        .await
+        .await?;
+    broadcast(SyncEvent::ChatsChanged);
+    Ok(chat)
 }
 
 pub async fn update_chat(chat_id: Uuid, request: UpdateChatRequest) -> Result<Chat, ApiError> {
     ApiClient::patch(&format!("/chats/{}", chat_id), &request)
         .authenticated()
+        .invalidates(CHATS_PATH)
         .send_json()
         .await
 }
@@ -37,6 +61,7 @@ pub async fn update_chat(chat_id: Uuid, request: UpdateChatRequest) -> Result<Ch
 pub async fn delete_chat(chat_id: Uuid) -> Result<(), ApiError> {
     ApiClient::delete(&format!("/chats/{}", chat_id))
         .authenticated()
+        .invalidates(CHATS_PATH)
         .send_empty()
         .await
 }
@@ -1,7 +1,10 @@
-use super::{base::ApiClient, error::ApiError};
-use crate::models::chat::{ChatMember, ChatMemberRole};
+use super::{base::ApiClient, cache, error::ApiError};
+use crate::models::chat::{ChatMember, ChatMemberRole, WsMessage, WsMessageType};
+use crate::utils::get_current_user_id;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
+use web_sys::AbortSignal;
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -27,9 +30,72 @@ pub struct DeleteChatMemberRequest {
     pub member_id: Uuid,
 }
 
-pub async fn get_chat_members(chat_id: Uuid) -> Result<Vec<ChatMember>, ApiError> {
-    ApiClient::get(&format!("/chats/members/{}", chat_id))
+/// Участников запрашивают и `Messages`, и `ChatMembersPanel` при открытии чата.
+const MEMBERS_TTL: Duration = Duration::from_secs(30);
+
+pub fn chat_members_path(chat_id: Uuid) -> String {
+    format!("/chats/members/{}", chat_id)
+}
+
+/// Чат, из которого событие исключает текущего пользователя: его выгнали или он вышел сам.
+pub fn removed_from(message: &WsMessage) -> Option<Uuid> {
+    if message.r#type != WsMessageType::MemberDeleted {
+        return None;
+    }
+    let removed = serde_json::from_value::<Uuid>(message.data.clone()).ok()?;
+    if Some(removed) != get_current_user_id() {
+        return None;
+    }
+    message.room_id
+}
+
+/// Изменение состава чата из событий `MemberUpdated` и `MemberDeleted`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemberChange {
+    Updated(ChatMember),
+    /// Идентификатор пользователя, а не записи участника.
+    Deleted(Uuid),
+}
+
+impl MemberChange {
+    /// Разбирает событие комнаты и сбрасывает кэш участников этого чата.
+    pub fn receive(message: &WsMessage) -> Option<Self> {
+        let change = match message.r#type {
+            WsMessageType::MemberUpdated => serde_json::from_value(message.data.clone())
+                .ok()
+                .map(Self::Updated),
+            WsMessageType::MemberDeleted => serde_json::from_value(message.data.clone())
+                .ok()
+                .map(Self::Deleted),
+            _ => None,
+        }?;
+        if let Some(chat_id) = message.room_id {
+            cache::invalidate(&chat_members_path(chat_id));
+        }
+        Some(change)
+    }
+
+    pub fn apply(&self, members: &mut Vec<ChatMember>) {
+        match self {
+            Self::Updated(member) => {
+                match members.iter_mut().find(|m| m.user_id == member.user_id) {
+                    Some(existing) => *existing = member.clone(),
+                    None => members.push(member.clone()),
+                }
+            }
+            Self::Deleted(user_id) => members.retain(|m| m.user_id != *user_id),
+        }
+    }
+}
+
+pub async fn get_chat_members(
+    chat_id: Uuid,
+    abort: Option<AbortSignal>,
+) -> Result<Vec<ChatMember>, ApiError> {
+    ApiClient::get(&chat_members_path(chat_id))
         .authenticated()
+        .abort_signal(abort)
+        .cached(MEMBERS_TTL)
         .send_json()
         .await
 }
@@ -37,6 +103,7 @@ pub async fn get_chat_members(chat_id: Uuid) -> Result<Vec<ChatMember>, ApiError
 pub async fn create_chat_member(request: CreateChatMemberRequest) -> Result<ChatMember, ApiError> {
     ApiClient::post("/chats/members", &request)
         .authenticated()
+        .invalidates(chat_members_path(request.chat_id))
         .send_json()
         .await
 }
@@ -44,6 +111,7 @@ pub async fn create_chat_member(request: CreateChatMemberRequest) -> Result<Chat
 pub async fn update_chat_member(request: UpdateChatMemberRequest) -> Result<ChatMember, ApiError> {
     ApiClient::patch("/chats/members", &request)
         .authenticated()
+        .invalidates(chat_members_path(request.chat_id))
         .send_json()
         .await
 }
@@ -51,6 +119,7 @@ pub async fn update_chat_member(request: UpdateChatMemberRequest) -> Result<Chat
 pub async fn delete_chat_member(request: DeleteChatMemberRequest) -> Result<(), ApiError> {
     ApiClient::delete_with_body("/chats/members", &request)
         .authenticated()
+        .invalidates(chat_members_path(request.chat_id))
         .send_empty()
         .await
 }
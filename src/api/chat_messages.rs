@@ -1,7 +1,9 @@
-use super::{base::ApiClient, error::ApiError};
+use super::{base::ApiClient, error::ApiError, retry::RetryPolicy};
 use crate::models::chat::ChatMessage;
+use crate::sync::{SyncEvent, broadcast, refresh_chats};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use web_sys::AbortSignal;
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -39,6 +41,7 @@ pub async fn get_chat_messages(
     pinned: Option<bool>,
     page: Option<u32>,
     page_size: Option<u32>,
+    abort: Option<AbortSignal>,
 ) -> Result<Vec<ChatMessage>, ApiError> {
     let pinned = pinned.unwrap_or(false);
     let page = page.unwrap_or(1);
@@ -47,16 +50,28 @@ pub async fn get_chat_messages(
         "/chats/messages/{}?page={}&pageSize={}&pinned={}",
         chat_id, page, page_size, pinned
     );
-    ApiClient::get(&path).authenticated().send_json().await
+    ApiClient::get(&path)
+        .authenticated()
+        .retry(RetryPolicy::default())
+        .abort_signal(abort)
+        .send_json()
+        .await
 }
 
+/// Сообщение по `receiver_id` создаёт личный чат, если его ещё не было,
+/// поэтому после него список чатов перезапрашивается во всех вкладках.
 pub async fn create_chat_message(
     request: CreateChatMessageRequest,
 ) -> Result<ChatMessage, ApiError> {
-    ApiClient::post("/chats/messages", &request)
+    let message = ApiClient::post("/chats/messages", &request)
         .authenticated()
         .send_json()
-        .await
+        .await?;
+    if request.receiver_id.is_some() {
+        refresh_chats();
+        broadcast(SyncEvent::ChatsChanged);
+    }
+    Ok(message)
 }
 
 pub async fn update_edit_chat_message(
@@ -1,8 +1,10 @@
+use super::transport::HttpResponse;
 use leptos_router::params::ParamsError;
-use reqwasm::http::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use thiserror::Error;
 
-#[derive(Error, Debug, Clone, PartialEq)]
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ApiError {
     #[error("Пользователь не авторизован")]
     Unauthorized,
@@ -10,24 +12,135 @@ pub enum ApiError {
     Network(String),
     #[error("Ошибка парсинга: {0}")]
     Parsing(String),
-    #[error("Ошибка сервера: {0}")]
-    Server(String),
-    #[error("Не найдено")]
-    NotFound,
+    #[error("Сервер не ответил вовремя")]
+    Timeout,
+    #[error("Запрос отменён")]
+    Aborted,
+    #[error("{}", .0.message_or("Некорректный запрос"))]
+    BadRequest(ErrorDetails),
+    #[error("{}", .0.message_or("Недостаточно прав"))]
+    Forbidden(ErrorDetails),
+    #[error("{}", .0.message_or("Не найдено"))]
+    NotFound(ErrorDetails),
+    #[error("{}", .0.message_or("Конфликт с текущим состоянием данных"))]
+    Conflict(ErrorDetails),
+    #[error("Ошибка сервера: {}", .0.message_or("неизвестная ошибка"))]
+    Server(ErrorDetails),
+}
+
+/// Тело ответа с ошибкой: текст сервера или ASP.NET problem details
+/// (`detail` и `errors` с ошибками по полям).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetails {
+    pub status: u16,
+    pub detail: Option<String>,
+    pub errors: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ProblemDetails {
+    detail: Option<String>,
+    #[serde(default)]
+    errors: BTreeMap<String, Vec<String>>,
+}
+
+impl ErrorDetails {
+    pub fn parse(status: u16, body: &str) -> Self {
+        let body = body.trim();
+        if let Ok(problem) = serde_json::from_str::<ProblemDetails>(body) {
+            return Self {
+                status,
+                detail: problem.detail,
+                errors: problem.errors,
+            };
+        }
+
+        let detail = serde_json::from_str::<String>(body).unwrap_or_else(|_| body.to_string());
+        Self {
+            status,
+            detail: (!detail.is_empty()).then_some(detail),
+            ..Default::default()
+        }
+    }
+
+    /// Ошибки валидации без имён полей, в порядке полей.
+    pub fn field_messages(&self) -> Vec<String> {
+        self.errors.values().flatten().cloned().collect()
+    }
+
+    /// Текст для пользователя: пояснение сервера, ошибки полей или `fallback`.
+    /// Стандартный английский `title` ASP.NET не показываем, поэтому и не храним.
+    pub fn message_or(&self, fallback: &str) -> String {
+        if let Some(detail) = &self.detail {
+            return detail.clone();
+        }
+        let fields = self.field_messages();
+        if !fields.is_empty() {
+            return fields.join("; ");
+        }
+        fallback.to_string()
+    }
 }
 
 impl From<ParamsError> for ApiError {
     fn from(_: ParamsError) -> Self {
-        ApiError::NotFound
+        ApiError::NotFound(ErrorDetails {
+            status: 404,
+            ..Default::default()
+        })
     }
 }
 
 impl ApiError {
-    pub async fn from_response(response: Response) -> Self {
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "Не удалось получить текст ошибки".to_string());
-        ApiError::Server(error_text)
+    pub fn from_response(response: HttpResponse) -> Self {
+        let status = response.status();
+        let details = ErrorDetails::parse(status, response.text());
+        match status {
+            400 | 422 => ApiError::BadRequest(details),
+            401 => ApiError::Unauthorized,
+            403 => ApiError::Forbidden(details),
+            404 => ApiError::NotFound(details),
+            409 => ApiError::Conflict(details),
+            _ => ApiError::Server(details),
+        }
+    }
+
+    /// HTTP-статус ответа, если ошибка пришла от сервера.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Unauthorized => Some(401),
+            _ => self.details().map(|d| d.status),
+        }
+    }
+
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            ApiError::BadRequest(d)
+            | ApiError::Forbidden(d)
+            | ApiError::NotFound(d)
+            | ApiError::Conflict(d)
+            | ApiError::Server(d) => Some(d),
+            ApiError::Unauthorized
+            | ApiError::Network(_)
+            | ApiError::Parsing(_)
+            | ApiError::Timeout
+            | ApiError::Aborted => None,
+        }
+    }
+
+    /// Сообщение для пользователя с подстановкой текстов для конкретного экрана.
+    /// Ошибки валидации по полям важнее подстановки, а если для статуса текст
+    /// не задан, используется описание ошибки по умолчанию.
+    pub fn localized(&self, messages: &[(u16, &str)]) -> String {
+        if self.details().is_some_and(|d| !d.errors.is_empty()) {
+            return self.to_string();
+        }
+        let custom = self.status().and_then(|status| {
+            messages
+                .iter()
+                .find(|(code, _)| *code == status)
+                .map(|(_, text)| text.to_string())
+        });
+        custom.unwrap_or_else(|| self.to_string())
     }
 }
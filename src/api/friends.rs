@@ -2,8 +2,12 @@ use super::{base::ApiClient, error::ApiError};
 use crate::models::friends::{Friendship, FriendshipLabel, FriendshipStatus};
 use crate::models::user::UserListItem;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
 
+/// Друзья меняются редко, а запрашиваются сразу несколькими экранами.
+const FRIENDS_TTL: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateFriendshipStatusRequest {
@@ -34,8 +38,12 @@ pub async fn get_friends(
 ) -> Result<Vec<Friendship>, ApiError> {
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(16);
-    let path = format!("/friends?page={}&pageSize={}",page, page_size);
-    ApiClient::get(&path).authenticated().send_json().await
+    let path = format!("/friends?page={}&pageSize={}", page, page_size);
+    ApiClient::get(&path)
+        .authenticated()
+        .cached(FRIENDS_TTL)
+        .send_json()
+        .await
 }
 
 pub async fn get_incoming_friend_requests(
@@ -45,7 +53,11 @@ pub async fn get_incoming_friend_requests(
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(16);
     let path = format!("/friends/incoming?page={}&pageSize={}", page, page_size);
-    ApiClient::get(&path).authenticated().send_json().await
+    ApiClient::get(&path)
+        .authenticated()
+        .cached(FRIENDS_TTL)
+        .send_json()
+        .await
 }
 
 pub async fn get_outgoing_friend_requests(
@@ -55,7 +67,11 @@ pub async fn get_outgoing_friend_requests(
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(16);
     let path = format!("/friends/outgoing?page={}&pageSize={}", page, page_size);
-    ApiClient::get(&path).authenticated().send_json().await
+    ApiClient::get(&path)
+        .authenticated()
+        .cached(FRIENDS_TTL)
+        .send_json()
+        .await
 }
 
 pub async fn get_possible_friends(
@@ -65,12 +81,18 @@ pub async fn get_possible_friends(
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(16);
     let path = format!("/friends/possible?page={}&pageSize={}", page, page_size);
-    ApiClient::get(&path).authenticated().send_json().await
+    ApiClient::get(&path)
+        .authenticated()
+        .cached(FRIENDS_TTL)
+        .send_json()
+        .await
 }
 
 pub async fn send_friend_request(friend_id: Uuid) -> Result<Friendship, ApiError> {
     ApiClient::post(&format!("/friends/{}", friend_id), &())
         .authenticated()
+        .invalidates("/friends")
+        .invalidates("/profile")
         .send_json()
         .await
 }
@@ -81,6 +103,8 @@ pub async fn update_friendship_status(
 ) -> Result<Friendship, ApiError> {
     ApiClient::patch(&format!("/friends/status/{}", friendship_id), &request)
         .authenticated()
+        .invalidates("/friends")
+        .invalidates("/profile")
         .send_json()
         .await
 }
@@ -88,6 +112,7 @@ pub async fn update_friendship_status(
 pub async fn get_friendship_labels() -> Result<Vec<FriendshipLabel>, ApiError> {
     ApiClient::get("/friends/labels")
         .authenticated()
+        .cached(FRIENDS_TTL)
         .send_json()
         .await
 }
@@ -97,6 +122,7 @@ pub async fn create_friendship_label(
 ) -> Result<FriendshipLabel, ApiError> {
     ApiClient::post("/friends/labels", &request)
         .authenticated()
+        .invalidates("/friends")
         .send_json()
         .await
 }
@@ -104,6 +130,7 @@ pub async fn create_friendship_label(
 pub async fn delete_friendship_label(friendship_label_id: Uuid) -> Result<(), ApiError> {
     ApiClient::delete(&format!("/friends/labels/{}", friendship_label_id))
         .authenticated()
+        .invalidates("/friends")
         .send_empty()
         .await
 }
@@ -111,6 +138,7 @@ pub async fn delete_friendship_label(friendship_label_id: Uuid) -> Result<(), Ap
 pub async fn delete_friendship_labels_by_label_value(label: String) -> Result<(), ApiError> {
     ApiClient::delete(&format!("/friends/labels?label={}", label))
         .authenticated()
+        .invalidates("/friends")
         .send_empty()
         .await
 }
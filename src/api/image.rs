@@ -1,7 +1,12 @@
-use super::{base::ApiClient, error::ApiError};
+use super::{base::ApiClient, error::ApiError, retry::RetryPolicy};
+use crate::sync::{SyncEvent, broadcast};
+use std::time::Duration;
 use uuid::Uuid;
 use web_sys::FormData;
 
+/// Картинки грузятся дольше обычных запросов, особенно с мобильной сети.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
 pub async fn upload_avatar(file: web_sys::File) -> Result<String, ApiError> {
     let form_data = FormData::new().unwrap();
     form_data
@@ -10,22 +15,21 @@ pub async fn upload_avatar(file: web_sys::File) -> Result<String, ApiError> {
 
     let response = ApiClient::post_form_data("/avatar", form_data)
         .authenticated()
-        .send_base()
+        .timeout(UPLOAD_TIMEOUT)
+        .send_text()
         .await?;
-
-    if !response.ok() {
-        return Err(ApiError::from_response(response).await);
-    }
-    response
-        .text()
-        .await
-        .map_err(|e| ApiError::Parsing(e.to_string()))
+    broadcast(SyncEvent::AvatarChanged);
+    Ok(response)
 }
 pub async fn delete_avatar() -> Result<String, ApiError> {
-    ApiClient::delete("/avatar")
+    let response = ApiClient::delete("/avatar")
         .authenticated()
+        .idempotent()
+        .retry(RetryPolicy::default())
         .send_text()
-        .await
+        .await?;
+    broadcast(SyncEvent::AvatarChanged);
+    Ok(response)
 }
 pub async fn upload_chat_image(chat_id: Uuid, file: web_sys::File) -> Result<String, ApiError> {
     let form_data = FormData::new().unwrap();
@@ -33,23 +37,18 @@ pub async fn upload_chat_image(chat_id: Uuid, file: web_sys::File) -> Result<Str
         .append_with_blob_and_filename("avatarFile", &file, &file.name())
         .unwrap();
 
-    let response = ApiClient::post_form_data(&format!("/chat-image/{}", chat_id), form_data)
+    ApiClient::post_form_data(&format!("/chat-image/{}", chat_id), form_data)
         .authenticated()
-        .send_base()
-        .await?;
-
-    if !response.ok() {
-        return Err(ApiError::from_response(response).await);
-    }
-    response
-        .text()
+        .timeout(UPLOAD_TIMEOUT)
+        .send_text()
         .await
-        .map_err(|e| ApiError::Parsing(e.to_string()))
 }
 
 pub async fn delete_chat_image(chat_id: Uuid) -> Result<String, ApiError> {
     ApiClient::delete(&format!("/chat-image/{}", chat_id))
         .authenticated()
+        .idempotent()
+        .retry(RetryPolicy::default())
         .send_text()
         .await
 }
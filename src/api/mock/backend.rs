@@ -0,0 +1,1444 @@
+use super::seed::seed;
+use crate::models::{
+    chat::{
+        Chat, ChatMember, ChatMemberRole, ChatMessage, ChatType, DEFAULT_MAX_MESSAGE_LENGTH,
+        PinnedMessageData, SystemMessageType, WsMessage, WsMessageType,
+    },
+    friends::{Friendship, FriendshipLabel, FriendshipStatus},
+    profile::Profile,
+    user::{User, UserListItem},
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+const JWT_SECRET: &[u8] = b"lunkvay-mock-secret";
+const TOKEN_LIFETIME_HOURS: i64 = 24;
+
+pub struct MockUser {
+    pub id: Uuid,
+    pub email: String,
+    pub password: String,
+    pub user_name: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub created_at: NaiveDateTime,
+    pub last_login: NaiveDateTime,
+    pub is_online: bool,
+    pub status: Option<String>,
+    pub about: Option<String>,
+}
+
+pub struct MockFriendship {
+    pub id: Uuid,
+    pub requester_id: Uuid,
+    pub addressee_id: Uuid,
+    pub status: FriendshipStatus,
+}
+
+pub struct MockLabel {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub friendship_id: Uuid,
+    pub label: String,
+}
+
+pub struct MockMember {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub member_name: Option<String>,
+    pub role: ChatMemberRole,
+}
+
+pub struct MockMessage {
+    pub id: Uuid,
+    pub sender_id: Option<Uuid>,
+    pub system_message_type: SystemMessageType,
+    pub message: String,
+    pub is_edited: bool,
+    pub is_pinned: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub pinned_at: Option<NaiveDateTime>,
+}
+
+pub struct MockChat {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub chat_type: ChatType,
+    pub created_at: NaiveDateTime,
+    pub members: Vec<MockMember>,
+    pub messages: Vec<MockMessage>,
+}
+
+impl MockChat {
+    fn member(&self, user_id: Uuid) -> Option<&MockMember> {
+        self.members.iter().find(|m| m.user_id == user_id)
+    }
+
+    fn last_activity(&self) -> NaiveDateTime {
+        self.messages
+            .last()
+            .map(|m| m.created_at)
+            .unwrap_or(self.created_at)
+    }
+}
+
+pub struct MockRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub token: Option<&'a str>,
+    pub body: Option<&'a str>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Событие для комнаты, которое настоящий сервер отправил бы по WebSocket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockEvent {
+    pub room_id: Uuid,
+    pub message: WsMessage,
+}
+
+type MockResult = Result<String, (u16, String)>;
+
+#[derive(Serialize, Deserialize)]
+struct MockClaims {
+    id: Uuid,
+    name: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct LoginPayload {
+    email: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterPayload {
+    email: String,
+    user_name: String,
+    password: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateProfilePayload {
+    new_status: Option<String>,
+    new_about: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FriendshipStatusPayload {
+    status: FriendshipStatus,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateLabelPayload {
+    friendship_id: Uuid,
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct CreateGroupChatPayload {
+    name: String,
+    members: Vec<Uuid>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateChatPayload {
+    new_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateMemberPayload {
+    chat_id: Uuid,
+    member_id: Uuid,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateMemberPayload {
+    chat_id: Uuid,
+    member_id: Uuid,
+    new_member_name: Option<String>,
+    new_role: Option<ChatMemberRole>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteMemberPayload {
+    chat_id: Uuid,
+    member_id: Uuid,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateMessagePayload {
+    chat_id: Option<Uuid>,
+    message: String,
+    receiver_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EditMessagePayload {
+    message_id: Uuid,
+    chat_id: Uuid,
+    new_message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinMessagePayload {
+    message_id: Uuid,
+    chat_id: Uuid,
+    is_pinned: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteMessagePayload {
+    message_id: Uuid,
+    chat_id: Uuid,
+}
+
+/// Состояние LunkvayAPI в памяти. Реализует контракт из `API_FRONTEND.md`
+/// и не зависит от браузера, поэтому его можно поднять и вне wasm.
+#[derive(Default)]
+pub struct MockBackend {
+    pub users: Vec<MockUser>,
+    pub friendships: Vec<MockFriendship>,
+    pub labels: Vec<MockLabel>,
+    pub chats: Vec<MockChat>,
+    events: Vec<MockEvent>,
+}
+
+impl MockBackend {
+    pub fn seeded() -> Self {
+        let mut backend = Self::default();
+        seed(&mut backend);
+        backend
+    }
+
+    /// Забирает накопленные события для рассылки подписчикам комнат.
+    pub fn take_events(&mut self) -> Vec<MockEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Идентификатор пользователя из `Bearer`-токена, если токен выдан этим бэкендом.
+    pub fn user_id_from_token(&self, token: &str) -> Option<Uuid> {
+        let validation = Validation::default();
+        let claims =
+            decode::<MockClaims>(token, &DecodingKey::from_secret(JWT_SECRET), &validation)
+                .ok()?
+                .claims;
+        self.user(claims.id).map(|u| u.id)
+    }
+
+    pub fn handle(&mut self, request: MockRequest) -> MockResponse {
+        let (path, query) = request.path.split_once('?').unwrap_or((request.path, ""));
+        let segments: Vec<String> = path
+            .trim_matches('/')
+            .split('/')
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let query = parse_query(query);
+
+        let result = self.route(
+            &request.method.to_ascii_uppercase(),
+            &segments,
+            &query,
+            request.token,
+            request.body.unwrap_or_default(),
+        );
+
+        match result {
+            Ok(body) => MockResponse { status: 200, body },
+            Err((status, body)) => MockResponse { status, body },
+        }
+    }
+
+    fn route(
+        &mut self,
+        method: &str,
+        segments: &[&str],
+        query: &[(String, String)],
+        token: Option<&str>,
+        body: &str,
+    ) -> MockResult {
+        match (method, segments) {
+            ("POST", ["auth", "login"]) => self.login(parse(body)?),
+            ("POST", ["auth", "logout"]) => Ok(String::new()),
+            ("POST", ["auth", "register"]) => self.register(parse(body)?),
+            ("GET", ["avatar", _]) | ("GET", ["chat-image", ..]) => {
+                Err((404, "Изображение не найдено".to_string()))
+            }
+            ("GET", ["profile", id]) if *id != "current-user-profile" => {
+                self.profile(parse_id(id)?)
+            }
+            ("GET", ["friends", id])
+                if !matches!(*id, "incoming" | "outgoing" | "possible" | "labels") =>
+            {
+                let user_id = parse_id(id)?;
+                self.friends_of(user_id, query)
+            }
+            _ => {
+                let user_id = self.authorize(token)?;
+                self.route_authorized(user_id, method, segments, query, body)
+            }
+        }
+    }
+
+    fn route_authorized(
+        &mut self,
+        me: Uuid,
+        method: &str,
+        segments: &[&str],
+        query: &[(String, String)],
+        body: &str,
+    ) -> MockResult {
+        match (method, segments) {
+            ("GET", ["avatar"]) => Err((404, "Изображение не найдено".to_string())),
+            ("POST", ["avatar"]) | ("DELETE", ["avatar"]) => Ok(String::new()),
+            ("POST", ["chat-image", id]) | ("DELETE", ["chat-image", id]) => {
+                let chat_id = parse_id(id)?;
+                self.chat_as_member(chat_id, me)?;
+                Ok(String::new())
+            }
+            ("GET", ["user", "all"]) => json(
+                &self
+                    .users
+                    .iter()
+                    .map(|u| self.user_dto(u.id))
+                    .collect::<Vec<_>>(),
+            ),
+            ("GET", ["user", id]) => {
+                let user_id = parse_id(id)?;
+                self.user_or_404(user_id)?;
+                json(&self.user_dto(user_id))
+            }
+            ("GET", ["profile", "current-user-profile"]) => self.profile(me),
+            ("PATCH", ["profile", "update"]) => self.update_profile(me, parse(body)?),
+            ("GET", ["friends"]) => self.friends_of(me, query),
+            ("GET", ["friends", "incoming"]) => {
+                let list = self.friendship_list(me, |f| {
+                    f.addressee_id == me && f.status == FriendshipStatus::Pending
+                });
+                json(&paginate(list, query, 10))
+            }
+            ("GET", ["friends", "outgoing"]) => {
+                let list = self.friendship_list(me, |f| {
+                    f.requester_id == me && f.status == FriendshipStatus::Pending
+                });
+                json(&paginate(list, query, 10))
+            }
+            ("GET", ["friends", "possible"]) => {
+                json(&paginate(self.possible_friends(me), query, 10))
+            }
+            ("GET", ["friends", "labels"]) => json(
+                &self
+                    .labels
+                    .iter()
+                    .filter(|l| l.owner_id == me)
+                    .map(label_dto)
+                    .collect::<Vec<_>>(),
+            ),
+            ("POST", ["friends", "labels"]) => self.create_label(me, parse(body)?),
+            ("DELETE", ["friends", "labels", id]) => {
+                let label_id = parse_id(id)?;
+                let before = self.labels.len();
+                self.labels
+                    .retain(|l| !(l.id == label_id && l.owner_id == me));
+                if self.labels.len() == before {
+                    return Err((404, "Метка не найдена".to_string()));
+                }
+                Ok(String::new())
+            }
+            ("DELETE", ["friends", "labels"]) => {
+                let label =
+                    query_value(query, "label").ok_or((400, "Не указана метка".to_string()))?;
+                self.labels
+                    .retain(|l| !(l.owner_id == me && l.label == label));
+                Ok(String::new())
+            }
+            ("PATCH", ["friends", "status", id]) => {
+                self.update_friendship_status(me, parse_id(id)?, parse(body)?)
+            }
+            ("POST", ["friends", id]) => self.send_friend_request(me, parse_id(id)?),
+            ("GET", ["chats"]) => {
+                let mut chats: Vec<&MockChat> = self
+                    .chats
+                    .iter()
+                    .filter(|c| c.member(me).is_some())
+                    .collect();
+                chats.sort_by_key(|c| std::cmp::Reverse(c.last_activity()));
+                json(
+                    &chats
+                        .into_iter()
+                        .map(|c| self.chat_dto(c, me))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ("POST", ["chats", "group"]) => self.create_group_chat(me, parse(body)?),
+            ("GET", ["chats", "members", id]) => {
+                let chat = self.chat_as_member(parse_id(id)?, me)?;
+                json(
+                    &chat
+                        .members
+                        .iter()
+                        .map(|m| self.member_dto(m))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ("POST", ["chats", "members"]) => self.create_member(me, parse(body)?),
+            ("PATCH", ["chats", "members"]) => self.update_member(me, parse(body)?),
+            ("DELETE", ["chats", "members"]) => self.delete_member(me, parse(body)?),
+            ("GET", ["chats", "messages", id]) => self.messages(me, parse_id(id)?, query),
+            ("POST", ["chats", "messages"]) => self.create_message(me, parse(body)?),
+            ("PATCH", ["chats", "messages", "edit"]) => self.edit_message(me, parse(body)?),
+            ("PATCH", ["chats", "messages", "pin"]) => self.pin_message(me, parse(body)?),
+            ("DELETE", ["chats", "messages"]) => self.delete_message(me, parse(body)?),
+            ("PATCH", ["chats", id]) => self.update_chat(me, parse_id(id)?, parse(body)?),
+            ("DELETE", ["chats", id]) => self.delete_chat(me, parse_id(id)?),
+            _ => Err((404, "Не найдено".to_string())),
+        }
+    }
+
+    // AUTH
+
+    fn authorize(&self, token: Option<&str>) -> Result<Uuid, (u16, String)> {
+        token
+            .and_then(|t| self.user_id_from_token(t))
+            .ok_or((401, "Пользователь не авторизован".to_string()))
+    }
+
+    fn issue_token(&self, user: &MockUser) -> String {
+        let now = Utc::now();
+        let claims = MockClaims {
+            id: user.id,
+            name: user.user_name.clone(),
+            iat: now.timestamp(),
+            exp: (now + Duration::hours(TOKEN_LIFETIME_HOURS)).timestamp(),
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(JWT_SECRET),
+        )
+        .unwrap_or_default()
+    }
+
+    fn login(&mut self, payload: LoginPayload) -> MockResult {
+        if payload.email.trim().is_empty() || payload.password.is_empty() {
+            return Err((400, "Email и пароль обязательны".to_string()));
+        }
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.email.eq_ignore_ascii_case(payload.email.trim()))
+            .filter(|u| u.password == payload.password)
+            .ok_or((401, "Неверный email или пароль".to_string()))?;
+        user.last_login = Utc::now().naive_utc();
+        user.is_online = true;
+        let user_id = user.id;
+        let user = self.user(user_id).ok_or((500, String::new()))?;
+        Ok(self.issue_token(user))
+    }
+
+    fn register(&mut self, payload: RegisterPayload) -> MockResult {
+        if payload.email.trim().is_empty()
+            || payload.user_name.trim().is_empty()
+            || payload.password.is_empty()
+        {
+            return Err((
+                400,
+                "Email, имя пользователя и пароль обязательны".to_string(),
+            ));
+        }
+        if self
+            .users
+            .iter()
+            .any(|u| u.email.eq_ignore_ascii_case(payload.email.trim()))
+        {
+            return Err((409, "Пользователь с таким email уже существует".to_string()));
+        }
+        if self
+            .users
+            .iter()
+            .any(|u| u.user_name.eq_ignore_ascii_case(payload.user_name.trim()))
+        {
+            return Err((409, "Имя пользователя уже занято".to_string()));
+        }
+        let now = Utc::now().naive_utc();
+        self.users.push(MockUser {
+            id: Uuid::new_v4(),
+            email: payload.email.trim().to_string(),
+            password: payload.password,
+            user_name: payload.user_name.trim().to_string(),
+            first_name: payload.first_name.unwrap_or_default(),
+            last_name: payload.last_name.unwrap_or_default(),
+            created_at: now,
+            last_login: now,
+            is_online: false,
+            status: None,
+            about: None,
+        });
+        Ok(String::new())
+    }
+
+    // PROFILE
+
+    fn profile(&self, user_id: Uuid) -> MockResult {
+        let user = self.user_or_404(user_id)?;
+        let friends: Vec<UserListItem> = self
+            .accepted_friend_ids(user_id)
+            .into_iter()
+            .filter_map(|id| self.user(id))
+            .map(user_list_item)
+            .collect();
+        json(&Profile {
+            id: user.id,
+            user: self.user_dto(user.id),
+            status: user.status.clone(),
+            about: user.about.clone(),
+            friends_count: friends.len() as i32,
+            friends,
+        })
+    }
+
+    fn update_profile(&mut self, me: Uuid, payload: UpdateProfilePayload) -> MockResult {
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.id == me)
+            .ok_or((404, "Пользователь не найден".to_string()))?;
+        if let Some(status) = payload.new_status {
+            user.status = Some(status);
+        }
+        if let Some(about) = payload.new_about {
+            user.about = Some(about);
+        }
+        self.profile(me)
+    }
+
+    // FRIENDS
+
+    fn friends_of(&self, user_id: Uuid, query: &[(String, String)]) -> MockResult {
+        self.user_or_404(user_id)?;
+        let list = self.friendship_list(user_id, |f| f.status == FriendshipStatus::Accepted);
+        json(&paginate(list, query, 10))
+    }
+
+    fn friendship_list(
+        &self,
+        viewer: Uuid,
+        filter: impl Fn(&MockFriendship) -> bool,
+    ) -> Vec<Friendship> {
+        self.friendships
+            .iter()
+            .filter(|f| f.requester_id == viewer || f.addressee_id == viewer)
+            .filter(|f| filter(f))
+            .filter_map(|f| self.friendship_dto(f, viewer))
+            .collect()
+    }
+
+    fn accepted_friend_ids(&self, user_id: Uuid) -> Vec<Uuid> {
+        self.friendships
+            .iter()
+            .filter(|f| f.status == FriendshipStatus::Accepted)
+            .filter_map(|f| other_side(f, user_id))
+            .collect()
+    }
+
+    fn possible_friends(&self, me: Uuid) -> Vec<UserListItem> {
+        self.users
+            .iter()
+            .filter(|u| u.id != me)
+            .filter(|u| {
+                !self
+                    .friendships
+                    .iter()
+                    .any(|f| is_active_between(f, me, u.id))
+            })
+            .map(user_list_item)
+            .collect()
+    }
+
+    fn send_friend_request(&mut self, me: Uuid, friend_id: Uuid) -> MockResult {
+        if friend_id == me {
+            return Err((400, "Нельзя отправить заявку самому себе".to_string()));
+        }
+        self.user_or_404(friend_id)?;
+        if self
+            .friendships
+            .iter()
+            .any(|f| is_active_between(f, me, friend_id))
+        {
+            return Err((409, "Заявка уже отправлена или вы уже друзья".to_string()));
+        }
+        let friendship = MockFriendship {
+            id: Uuid::new_v4(),
+            requester_id: me,
+            addressee_id: friend_id,
+            status: FriendshipStatus::Pending,
+        };
+        let dto = self.friendship_dto(&friendship, me);
+        self.friendships.push(friendship);
+        json(&dto)
+    }
+
+    fn update_friendship_status(
+        &mut self,
+        me: Uuid,
+        friendship_id: Uuid,
+        payload: FriendshipStatusPayload,
+    ) -> MockResult {
+        let friendship = self
+            .friendships
+            .iter_mut()
+            .find(|f| f.id == friendship_id && (f.requester_id == me || f.addressee_id == me))
+            .ok_or((404, "Дружба не найдена".to_string()))?;
+
+        let allowed = match (&friendship.status, &payload.status) {
+            (FriendshipStatus::Pending, FriendshipStatus::Accepted)
+            | (FriendshipStatus::Pending, FriendshipStatus::Rejected) => {
+                friendship.addressee_id == me
+            }
+            (FriendshipStatus::Pending, FriendshipStatus::Cancelled) => {
+                friendship.requester_id == me
+            }
+            (FriendshipStatus::Accepted, FriendshipStatus::Deleted) => true,
+            _ => false,
+        };
+        if !allowed {
+            return Err((400, "Недопустимое изменение статуса".to_string()));
+        }
+        friendship.status = payload.status;
+
+        let friendship = self
+            .friendships
+            .iter()
+            .find(|f| f.id == friendship_id)
+            .ok_or((404, "Дружба не найдена".to_string()))?;
+        json(&self.friendship_dto(friendship, me))
+    }
+
+    fn create_label(&mut self, me: Uuid, payload: CreateLabelPayload) -> MockResult {
+        let label = payload.label.trim().to_string();
+        if label.is_empty() {
+            return Err((400, "Метка не может быть пустой".to_string()));
+        }
+        if !self.friendships.iter().any(|f| {
+            f.id == payload.friendship_id && (f.requester_id == me || f.addressee_id == me)
+        }) {
+            return Err((400, "Дружба не найдена".to_string()));
+        }
+        let label = MockLabel {
+            id: Uuid::new_v4(),
+            owner_id: me,
+            friendship_id: payload.friendship_id,
+            label,
+        };
+        let dto = label_dto(&label);
+        self.labels.push(label);
+        json(&dto)
+    }
+
+    // CHATS
+
+    fn create_group_chat(&mut self, me: Uuid, payload: CreateGroupChatPayload) -> MockResult {
+        let name = payload.name.trim().to_string();
+        if name.is_empty() {
+            return Err((400, "Название чата не может быть пустым".to_string()));
+        }
+        let now = Utc::now().naive_utc();
+        let mut members = vec![MockMember {
+            id: Uuid::new_v4(),
+            user_id: me,
+            member_name: None,
+            role: ChatMemberRole::Owner,
+        }];
+        for user_id in payload.members {
+            if user_id != me
+                && self.user(user_id).is_some()
+                && !members.iter().any(|m| m.user_id == user_id)
+            {
+                members.push(MockMember {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    member_name: None,
+                    role: ChatMemberRole::Member,
+                });
+            }
+        }
+        let chat = MockChat {
+            id: Uuid::new_v4(),
+            name: Some(name.clone()),
+            chat_type: ChatType::Group,
+            created_at: now,
+            members,
+            messages: vec![system_message(
+                SystemMessageType::ChatCreated,
+                format!("{} создал(а) чат «{}»", self.display_name(me), name),
+            )],
+        };
+        let dto = self.chat_dto(&chat, me);
+        self.chats.push(chat);
+        json(&dto)
+    }
+
+    fn update_chat(&mut self, me: Uuid, chat_id: Uuid, payload: UpdateChatPayload) -> MockResult {
+        let role = self.role_in(chat_id, me)?;
+        if role == ChatMemberRole::Member {
+            return Err((403, "Недостаточно прав для изменения чата".to_string()));
+        }
+        let new_name = payload
+            .new_name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .ok_or((400, "Название чата не может быть пустым".to_string()))?;
+        let text = format!(
+            "{} переименовал(а) чат в «{}»",
+            self.display_name(me),
+            new_name
+        );
+
+        let chat = self.chat_mut(chat_id)?;
+        chat.name = Some(new_name);
+        chat.messages
+            .push(system_message(SystemMessageType::ChatUpdated, text));
+
+        let chat = self
+            .chat(chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        let dto = self.chat_dto(chat, me);
+        self.emit(chat_id, WsMessageType::ChatUpdated, &dto);
+        json(&dto)
+    }
+
+    fn delete_chat(&mut self, me: Uuid, chat_id: Uuid) -> MockResult {
+        let role = self.role_in(chat_id, me)?;
+        let is_group = self
+            .chat(chat_id)
+            .is_some_and(|c| c.chat_type == ChatType::Group);
+        if is_group && role != ChatMemberRole::Owner {
+            return Err((403, "Удалить чат может только владелец".to_string()));
+        }
+        self.chats.retain(|c| c.id != chat_id);
+        self.emit(chat_id, WsMessageType::ChatDeleted, &chat_id);
+        Ok(String::new())
+    }
+
+    // MEMBERS
+
+    fn create_member(&mut self, me: Uuid, payload: CreateMemberPayload) -> MockResult {
+        self.role_in(payload.chat_id, me)?;
+        self.user_or_404(payload.member_id)?;
+        let chat = self.chat_mut(payload.chat_id)?;
+        if chat.chat_type == ChatType::Personal {
+            return Err((400, "Нельзя приглашать в личный чат".to_string()));
+        }
+        if chat.member(payload.member_id).is_some() {
+            return Err((400, "Пользователь уже состоит в чате".to_string()));
+        }
+        chat.members.push(MockMember {
+            id: Uuid::new_v4(),
+            user_id: payload.member_id,
+            member_name: None,
+            role: ChatMemberRole::Member,
+        });
+
+        let text = format!(
+            "{} пригласил(а) {}",
+            self.display_name(me),
+            self.display_name(payload.member_id)
+        );
+        self.push_message(
+            payload.chat_id,
+            system_message(SystemMessageType::UserJoined, text),
+        )?;
+
+        let chat = self
+            .chat(payload.chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        let member = chat
+            .member(payload.member_id)
+            .ok_or((404, "Участник не найден".to_string()))?;
+        json(&self.member_dto(member))
+    }
+
+    fn update_member(&mut self, me: Uuid, payload: UpdateMemberPayload) -> MockResult {
+        let my_role = self.role_in(payload.chat_id, me)?;
+        let target_role = self.role_in(payload.chat_id, payload.member_id)?;
+
+        if payload.new_role.is_some()
+            && (my_role != ChatMemberRole::Owner || payload.member_id == me)
+        {
+            return Err((403, "Менять роли может только владелец".to_string()));
+        }
+        if payload.new_role == Some(ChatMemberRole::Owner) {
+            return Err((400, "Нельзя назначить второго владельца".to_string()));
+        }
+        let can_rename = payload.member_id == me
+            || my_role == ChatMemberRole::Owner
+            || (my_role == ChatMemberRole::Administrator && target_role != ChatMemberRole::Owner);
+        if payload.new_member_name.is_some() && !can_rename {
+            return Err((403, "Недостаточно прав для изменения имени".to_string()));
+        }
+
+        let chat = self.chat_mut(payload.chat_id)?;
+        let member = chat
+            .members
+            .iter_mut()
+            .find(|m| m.user_id == payload.member_id)
+            .ok_or((404, "Участник не найден".to_string()))?;
+        if let Some(name) = payload.new_member_name {
+            let name = name.trim().to_string();
+            member.member_name = (!name.is_empty()).then_some(name);
+        }
+        if let Some(role) = payload.new_role {
+            member.role = role;
+        }
+
+        let chat = self
+            .chat(payload.chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        let member = chat
+            .member(payload.member_id)
+            .ok_or((404, "Участник не найден".to_string()))?;
+        let dto = self.member_dto(member);
+        self.emit(payload.chat_id, WsMessageType::MemberUpdated, &dto);
+        json(&dto)
+    }
+
+    fn delete_member(&mut self, me: Uuid, payload: DeleteMemberPayload) -> MockResult {
+        let my_role = self.role_in(payload.chat_id, me)?;
+        let target_role = self.role_in(payload.chat_id, payload.member_id)?;
+        let allowed = payload.member_id == me
+            || (my_role == ChatMemberRole::Owner)
+            || (my_role == ChatMemberRole::Administrator && target_role == ChatMemberRole::Member);
+        if !allowed {
+            return Err((403, "Недостаточно прав для удаления участника".to_string()));
+        }
+
+        let text = if payload.member_id == me {
+            format!("{} покинул(а) чат", self.display_name(me))
+        } else {
+            format!(
+                "{} исключил(а) {}",
+                self.display_name(me),
+                self.display_name(payload.member_id)
+            )
+        };
+        self.emit(
+            payload.chat_id,
+            WsMessageType::MemberDeleted,
+            &payload.member_id,
+        );
+        let chat = self.chat_mut(payload.chat_id)?;
+        chat.members.retain(|m| m.user_id != payload.member_id);
+        self.push_message(
+            payload.chat_id,
+            system_message(SystemMessageType::UserLeft, text),
+        )?;
+        Ok(String::new())
+    }
+
+    // MESSAGES
+
+    fn messages(&self, me: Uuid, chat_id: Uuid, query: &[(String, String)]) -> MockResult {
+        let chat = self.chat_as_member(chat_id, me)?;
+        let pinned = query_value(query, "pinned").is_some_and(|v| v == "true");
+
+        let mut list: Vec<&MockMessage> = chat
+            .messages
+            .iter()
+            .filter(|m| !pinned || m.is_pinned)
+            .collect();
+        if pinned {
+            list.sort_by_key(|m| std::cmp::Reverse(m.pinned_at));
+        } else {
+            list.reverse();
+        }
+        let list: Vec<ChatMessage> = list.into_iter().map(|m| self.message_dto(m, me)).collect();
+        json(&paginate(list, query, 10))
+    }
+
+    fn create_message(&mut self, me: Uuid, payload: CreateMessagePayload) -> MockResult {
+        check_message_text(&payload.message)?;
+        let chat_id = match (payload.chat_id, payload.receiver_id) {
+            (Some(chat_id), _) => {
+                self.role_in(chat_id, me)?;
+                chat_id
+            }
+            (None, Some(receiver_id)) => self.personal_chat_with(me, receiver_id)?,
+            (None, None) => return Err((400, "Не указан чат или получатель".to_string())),
+        };
+
+        let message = MockMessage {
+            id: Uuid::new_v4(),
+            sender_id: Some(me),
+            system_message_type: SystemMessageType::None,
+            message: payload.message,
+            is_edited: false,
+            is_pinned: false,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            pinned_at: None,
+        };
+        let message_id = message.id;
+        self.push_message(chat_id, message)?;
+        let chat = self
+            .chat(chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        let message = chat
+            .messages
+            .iter()
+            .find(|m| m.id == message_id)
+            .ok_or((500, String::new()))?;
+        json(&self.message_dto(message, me))
+    }
+
+    fn edit_message(&mut self, me: Uuid, payload: EditMessagePayload) -> MockResult {
+        self.role_in(payload.chat_id, me)?;
+        check_message_text(&payload.new_message)?;
+        let chat = self.chat_mut(payload.chat_id)?;
+        let message = chat
+            .messages
+            .iter_mut()
+            .find(|m| m.id == payload.message_id)
+            .ok_or((404, "Сообщение не найдено".to_string()))?;
+        if message.sender_id != Some(me) {
+            return Err((403, "Можно редактировать только свои сообщения".to_string()));
+        }
+        message.message = payload.new_message;
+        message.is_edited = true;
+        message.updated_at = Some(Utc::now().naive_utc());
+
+        let dto = self.find_message_dto(payload.chat_id, payload.message_id, me)?;
+        self.emit(
+            payload.chat_id,
+            WsMessageType::MessageUpdated,
+            &ChatMessage {
+                is_my_message: false,
+                ..dto.clone()
+            },
+        );
+        json(&dto)
+    }
+
+    fn pin_message(&mut self, me: Uuid, payload: PinMessagePayload) -> MockResult {
+        self.role_in(payload.chat_id, me)?;
+        let now = Utc::now().naive_utc();
+        let chat = self.chat_mut(payload.chat_id)?;
+        let message = chat
+            .messages
+            .iter_mut()
+            .find(|m| m.id == payload.message_id)
+            .ok_or((404, "Сообщение не найдено".to_string()))?;
+        message.is_pinned = payload.is_pinned;
+        message.pinned_at = payload.is_pinned.then_some(now);
+        message.updated_at = Some(now);
+
+        self.emit(
+            payload.chat_id,
+            WsMessageType::MessagePinned,
+            &PinnedMessageData {
+                message_id: payload.message_id,
+                is_pinned: payload.is_pinned,
+                updated_at: Some(now),
+            },
+        );
+        json(&self.find_message_dto(payload.chat_id, payload.message_id, me)?)
+    }
+
+    fn delete_message(&mut self, me: Uuid, payload: DeleteMessagePayload) -> MockResult {
+        let role = self.role_in(payload.chat_id, me)?;
+        let chat = self.chat_mut(payload.chat_id)?;
+        let message = chat
+            .messages
+            .iter()
+            .find(|m| m.id == payload.message_id)
+            .ok_or((404, "Сообщение не найдено".to_string()))?;
+        if message.sender_id != Some(me) && role == ChatMemberRole::Member {
+            return Err((403, "Можно удалять только свои сообщения".to_string()));
+        }
+        chat.messages.retain(|m| m.id != payload.message_id);
+        self.emit(
+            payload.chat_id,
+            WsMessageType::MessageDeleted,
+            &payload.message_id,
+        );
+        Ok(String::new())
+    }
+
+    // HELPERS
+
+    fn user(&self, user_id: Uuid) -> Option<&MockUser> {
+        self.users.iter().find(|u| u.id == user_id)
+    }
+
+    fn user_or_404(&self, user_id: Uuid) -> Result<&MockUser, (u16, String)> {
+        self.user(user_id)
+            .ok_or((404, "Пользователь не найден".to_string()))
+    }
+
+    fn display_name(&self, user_id: Uuid) -> String {
+        self.user(user_id)
+            .map(|u| format!("{} {}", u.first_name, u.last_name))
+            .unwrap_or_default()
+    }
+
+    fn chat(&self, chat_id: Uuid) -> Option<&MockChat> {
+        self.chats.iter().find(|c| c.id == chat_id)
+    }
+
+    fn chat_mut(&mut self, chat_id: Uuid) -> Result<&mut MockChat, (u16, String)> {
+        self.chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or((404, "Чат не найден".to_string()))
+    }
+
+    fn chat_as_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<&MockChat, (u16, String)> {
+        let chat = self
+            .chat(chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        if chat.member(user_id).is_none() {
+            return Err((403, "Вы не состоите в этом чате".to_string()));
+        }
+        Ok(chat)
+    }
+
+    fn role_in(&self, chat_id: Uuid, user_id: Uuid) -> Result<ChatMemberRole, (u16, String)> {
+        let chat = self
+            .chat(chat_id)
+            .ok_or((404, "Чат не найден".to_string()))?;
+        chat.member(user_id)
+            .map(|m| m.role)
+            .ok_or((403, "Пользователь не состоит в этом чате".to_string()))
+    }
+
+    fn personal_chat_with(&mut self, me: Uuid, receiver_id: Uuid) -> Result<Uuid, (u16, String)> {
+        if receiver_id == me {
+            return Err((400, "Нельзя написать самому себе".to_string()));
+        }
+        self.user_or_404(receiver_id)?;
+        if let Some(chat) = self.chats.iter().find(|c| {
+            c.chat_type == ChatType::Personal
+                && c.member(me).is_some()
+                && c.member(receiver_id).is_some()
+        }) {
+            return Ok(chat.id);
+        }
+        let chat = MockChat {
+            id: Uuid::new_v4(),
+            name: None,
+            chat_type: ChatType::Personal,
+            created_at: Utc::now().naive_utc(),
+            members: [me, receiver_id]
+                .into_iter()
+                .map(|user_id| MockMember {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    member_name: None,
+                    role: ChatMemberRole::Member,
+                })
+                .collect(),
+            messages: Vec::new(),
+        };
+        let chat_id = chat.id;
+        self.chats.push(chat);
+        Ok(chat_id)
+    }
+
+    fn push_message(&mut self, chat_id: Uuid, message: MockMessage) -> Result<(), (u16, String)> {
+        let dto = self.message_dto(&message, Uuid::nil());
+        self.chat_mut(chat_id)?.messages.push(message);
+        self.emit(chat_id, WsMessageType::ReceiveMessage, &dto);
+        Ok(())
+    }
+
+    fn find_message_dto(
+        &self,
+        chat_id: Uuid,
+        message_id: Uuid,
+        viewer: Uuid,
+    ) -> Result<ChatMessage, (u16, String)> {
+        self.chat(chat_id)
+            .and_then(|c| c.messages.iter().find(|m| m.id == message_id))
+            .map(|m| self.message_dto(m, viewer))
+            .ok_or((404, "Сообщение не найдено".to_string()))
+    }
+
+    fn emit<T: Serialize>(&mut self, room_id: Uuid, r#type: WsMessageType, data: &T) {
+        if let Ok(data) = serde_json::to_value(data) {
+            self.events.push(MockEvent {
+                room_id,
+                message: WsMessage {
+                    room_id: Some(room_id),
+                    r#type,
+                    data,
+                },
+            });
+        }
+    }
+
+    // DTO
+
+    fn user_dto(&self, user_id: Uuid) -> User {
+        let user = self.user(user_id);
+        User {
+            id: user_id,
+            user_name: user.map(|u| u.user_name.clone()).unwrap_or_default(),
+            first_name: user.map(|u| u.first_name.clone()).unwrap_or_default(),
+            last_name: user.map(|u| u.last_name.clone()).unwrap_or_default(),
+            created_at: user.map(|u| u.created_at).unwrap_or_default(),
+            is_deleted: user.is_none(),
+            last_login: user.map(|u| u.last_login).unwrap_or_default(),
+            is_online: user.is_some_and(|u| u.is_online),
+        }
+    }
+
+    fn friendship_dto(&self, friendship: &MockFriendship, viewer: Uuid) -> Option<Friendship> {
+        let other = self.user(other_side(friendship, viewer)?)?;
+        let labels = self
+            .labels
+            .iter()
+            .filter(|l| l.friendship_id == friendship.id && l.owner_id == viewer)
+            .map(label_dto)
+            .collect();
+        Some(Friendship {
+            friendship_id: friendship.id,
+            status: Some(friendship.status.clone()),
+            labels: Some(labels),
+            user_id: other.id,
+            user_name: other.user_name.clone(),
+            first_name: other.first_name.clone(),
+            last_name: other.last_name.clone(),
+            is_online: other.is_online,
+        })
+    }
+
+    fn member_dto(&self, member: &MockMember) -> ChatMember {
+        let user = self.user_dto(member.user_id);
+        ChatMember {
+            id: member.id,
+            user_id: member.user_id,
+            user_name: user.user_name,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            member_name: member.member_name.clone(),
+            is_online: user.is_online,
+            role: member.role,
+        }
+    }
+
+    fn message_dto(&self, message: &MockMessage, viewer: Uuid) -> ChatMessage {
+        let sender = message.sender_id.and_then(|id| self.user(id));
+        ChatMessage {
+            id: message.id,
+            sender_id: message.sender_id,
+            sender_user_name: sender.map(|u| u.user_name.clone()),
+            sender_first_name: sender.map(|u| u.first_name.clone()),
+            sender_last_name: sender.map(|u| u.last_name.clone()),
+            sender_is_online: sender.map(|u| u.is_online),
+            system_message_type: message.system_message_type.clone(),
+            message: message.message.clone(),
+            is_edited: message.is_edited,
+            is_pinned: message.is_pinned,
+            created_at: message.created_at,
+            updated_at: message.updated_at,
+            pinned_at: message.pinned_at,
+            is_my_message: message.sender_id == Some(viewer),
+        }
+    }
+
+    fn chat_dto(&self, chat: &MockChat, viewer: Uuid) -> Chat {
+        let name = match chat.chat_type {
+            ChatType::Group => chat.name.clone(),
+            ChatType::Personal => chat
+                .members
+                .iter()
+                .find(|m| m.user_id != viewer)
+                .map(|m| self.display_name(m.user_id)),
+        };
+        Chat {
+            id: chat.id,
+            name,
+            last_message: chat.messages.last().map(|m| self.message_dto(m, viewer)),
+            chat_type: chat.chat_type.clone(),
+            created_at: chat.created_at,
+            member_count: chat.members.len() as i32,
+        }
+    }
+}
+
+fn system_message(system_message_type: SystemMessageType, text: String) -> MockMessage {
+    MockMessage {
+        id: Uuid::new_v4(),
+        sender_id: None,
+        system_message_type,
+        message: text,
+        is_edited: false,
+        is_pinned: false,
+        created_at: Utc::now().naive_utc(),
+        updated_at: None,
+        pinned_at: None,
+    }
+}
+
+fn check_message_text(text: &str) -> Result<(), (u16, String)> {
+    if text.trim().is_empty() {
+        return Err((400, "Сообщение не может быть пустым".to_string()));
+    }
+    if text.chars().count() > DEFAULT_MAX_MESSAGE_LENGTH {
+        return Err((
+            400,
+            format!("Сообщение длиннее {} символов", DEFAULT_MAX_MESSAGE_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn other_side(friendship: &MockFriendship, user_id: Uuid) -> Option<Uuid> {
+    if friendship.requester_id == user_id {
+        Some(friendship.addressee_id)
+    } else if friendship.addressee_id == user_id {
+        Some(friendship.requester_id)
+    } else {
+        None
+    }
+}
+
+fn is_active_between(friendship: &MockFriendship, a: Uuid, b: Uuid) -> bool {
+    other_side(friendship, a) == Some(b)
+        && matches!(
+            friendship.status,
+            FriendshipStatus::Pending | FriendshipStatus::Accepted
+        )
+}
+
+fn user_list_item(user: &MockUser) -> UserListItem {
+    UserListItem {
+        user_id: user.id,
+        user_name: user.user_name.clone(),
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone(),
+        is_online: user.is_online,
+    }
+}
+
+fn label_dto(label: &MockLabel) -> FriendshipLabel {
+    FriendshipLabel {
+        id: label.id,
+        label: label.label.clone(),
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> MockResult {
+    serde_json::to_string(value).map_err(|e| (500, e.to_string()))
+}
+
+fn parse<T: DeserializeOwned>(body: &str) -> Result<T, (u16, String)> {
+    serde_json::from_str(body).map_err(|e| (400, format!("Некорректное тело запроса: {}", e)))
+}
+
+fn parse_id(segment: &str) -> Result<Uuid, (u16, String)> {
+    Uuid::parse_str(segment).map_err(|_| (400, "Некорректный идентификатор".to_string()))
+}
+
+fn paginate<T>(list: Vec<T>, query: &[(String, String)], default_page_size: usize) -> Vec<T> {
+    let page = query_value(query, "page")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let page_size = query_value(query, "pagesize")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(default_page_size)
+        .clamp(1, 100);
+    list.into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .collect()
+}
+
+fn query_value<'a>(query: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push((high * 16 + low) as u8);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::seed::{KEANU, RYAN};
+    use super::*;
+
+    fn request<'a>(
+        method: &'a str,
+        path: &'a str,
+        token: Option<&'a str>,
+        body: Option<&'a str>,
+    ) -> MockRequest<'a> {
+        MockRequest {
+            method,
+            path,
+            token,
+            body,
+        }
+    }
+
+    fn login(backend: &mut MockBackend) -> String {
+        let body = r#"{"email":"ryan.gosling@gmail.com","password":"realhero"}"#;
+        let response = backend.handle(request("POST", "/auth/login", None, Some(body)));
+        assert_eq!(response.status, 200);
+        response.body
+    }
+
+    fn chats(backend: &mut MockBackend, token: &str) -> Vec<Chat> {
+        let response = backend.handle(request("GET", "/chats", Some(token), None));
+        assert_eq!(response.status, 200);
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn login_issues_token_for_seeded_user() {
+        let mut backend = MockBackend::seeded();
+        let token = login(&mut backend);
+        assert_eq!(backend.user_id_from_token(&token), Some(RYAN));
+    }
+
+    #[test]
+    fn wrong_password_is_unauthorized() {
+        let mut backend = MockBackend::seeded();
+        let body = r#"{"email":"ryan.gosling@gmail.com","password":"wrong"}"#;
+        let response = backend.handle(request("POST", "/auth/login", None, Some(body)));
+        assert_eq!(response.status, 401);
+    }
+
+    #[test]
+    fn private_routes_require_token() {
+        let mut backend = MockBackend::seeded();
+        assert_eq!(
+            backend.handle(request("GET", "/chats", None, None)).status,
+            401
+        );
+        assert_eq!(
+            backend
+                .handle(request("GET", "/chats", Some("garbage"), None))
+                .status,
+            401
+        );
+    }
+
+    #[test]
+    fn public_routes_work_without_token() {
+        let mut backend = MockBackend::seeded();
+        let path = format!("/profile/{}", KEANU);
+        let response = backend.handle(request("GET", &path, None, None));
+        assert_eq!(response.status, 200);
+        let profile: Profile = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(profile.user.id, KEANU);
+    }
+
+    #[test]
+    fn unknown_route_is_not_found() {
+        let mut backend = MockBackend::seeded();
+        let token = login(&mut backend);
+        let response = backend.handle(request("GET", "/nothing/here", Some(&token), None));
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn routes_ignore_case_slashes_and_query() {
+        let mut backend = MockBackend::seeded();
+        let token = login(&mut backend);
+        let chat_id = chats(&mut backend, &token)[0].id;
+
+        let path = format!("/Chats/Messages/{}/?page=1&pageSize=2", chat_id);
+        let response = backend.handle(request("get", &path, Some(&token), None));
+        assert_eq!(response.status, 200);
+        let messages: Vec<ChatMessage> = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn sent_message_is_emitted_to_its_room() {
+        let mut backend = MockBackend::seeded();
+        let token = login(&mut backend);
+        let chat_id = chats(&mut backend, &token)[0].id;
+        backend.take_events();
+
+        let body = format!(r#"{{"chatId":"{}","message":"Привет"}}"#, chat_id);
+        let response = backend.handle(request(
+            "POST",
+            "/chats/messages",
+            Some(&token),
+            Some(&body),
+        ));
+        assert_eq!(response.status, 200);
+        let sent: ChatMessage = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(sent.message, "Привет");
+
+        let events = backend.take_events();
+        assert!(events.iter().any(|event| event.room_id == chat_id
+            && event.message.r#type == WsMessageType::ReceiveMessage));
+    }
+
+    #[test]
+    fn invalid_body_is_bad_request() {
+        let mut backend = MockBackend::seeded();
+        let token = login(&mut backend);
+        let response = backend.handle(request("POST", "/chats/messages", Some(&token), Some("{")));
+        assert_eq!(response.status, 400);
+    }
+}
@@ -0,0 +1,136 @@
+pub mod backend;
+pub mod seed;
+
+use super::transport::{
+    HttpRequest, HttpResponse, RequestBody, Transport, TransportFuture, set_transport,
+};
+use crate::models::chat::WsMessage;
+use backend::{MockBackend, MockEvent, MockRequest};
+use leptos::prelude::*;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+use uuid::Uuid;
+
+const MOCK_FLAG_KEY: &str = "mock";
+
+type Listener = Rc<dyn Fn(WsMessage)>;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static NEXT_LISTENER_ID: Cell<u64> = const { Cell::new(0) };
+    static LISTENERS: RefCell<Vec<(u64, Uuid, Listener)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Транспорт без сети: запросы обрабатывает `MockBackend` прямо в браузере.
+pub struct MockTransport {
+    backend: Rc<RefCell<MockBackend>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self {
+            backend: Rc::new(RefCell::new(MockBackend::seeded())),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture {
+        let body = match &request.body {
+            RequestBody::Json(body) => Some(body.as_str()),
+            RequestBody::Empty | RequestBody::FormData(_) => None,
+        };
+        let method = request.method.to_string();
+
+        let (response, events) = {
+            let mut backend = self.backend.borrow_mut();
+            let response = backend.handle(MockRequest {
+                method: &method,
+                path: &request.path,
+                token: request.token.as_deref(),
+                body,
+            });
+            (response, backend.take_events())
+        };
+        dispatch(events);
+
+        Box::pin(std::future::ready(Ok(HttpResponse::new(
+            response.status,
+            response.body,
+        ))))
+    }
+}
+
+/// Включает мок-бэкенд, если приложение собрано с фичей `mock` или открыто с `?mock=1`.
+/// Флаг из адреса запоминается в sessionStorage, `?mock=0` его сбрасывает.
+/// В релизной сборке без фичи `mock` модуля нет вовсе.
+pub fn install_if_requested() {
+    let session = window().session_storage().ok().flatten();
+    let from_query = window().location().search().ok().and_then(|search| {
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("mock="))
+            .map(|value| value == "1" || value == "true")
+    });
+
+    if let (Some(session), Some(enabled)) = (&session, from_query) {
+        let _ = if enabled {
+            session.set_item(MOCK_FLAG_KEY, "1")
+        } else {
+            session.remove_item(MOCK_FLAG_KEY)
+        };
+    }
+
+    let remembered = session
+        .and_then(|s| s.get_item(MOCK_FLAG_KEY).ok().flatten())
+        .is_some();
+
+    if cfg!(feature = "mock") || from_query.unwrap_or(remembered) {
+        set_transport(MockTransport::new());
+        ENABLED.with(|e| e.set(true));
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Подписка на события комнаты мок-бэкенда — замена `/ws?roomId=` в офлайн-режиме.
+pub fn subscribe(room_id: Uuid, listener: impl Fn(WsMessage) + 'static) -> u64 {
+    let id = NEXT_LISTENER_ID.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    });
+    LISTENERS.with(|l| l.borrow_mut().push((id, room_id, Rc::new(listener))));
+    id
+}
+
+pub fn unsubscribe(id: u64) {
+    LISTENERS.with(|l| {
+        l.borrow_mut()
+            .retain(|(listener_id, _, _)| *listener_id != id)
+    });
+}
+
+fn dispatch(events: Vec<MockEvent>) {
+    for event in events {
+        let listeners: Vec<Listener> = LISTENERS.with(|l| {
+            l.borrow()
+                .iter()
+                .filter(|(_, room_id, _)| *room_id == event.room_id)
+                .map(|(_, _, listener)| listener.clone())
+                .collect()
+        });
+        // Каждое событие доставляется отдельной задачей, как кадры настоящего сокета,
+        // чтобы эффекты успели обработать их по одному.
+        for listener in listeners {
+            let message = event.message.clone();
+            set_timeout(move || listener(message), Duration::ZERO);
+        }
+    }
+}
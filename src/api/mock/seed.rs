@@ -0,0 +1,284 @@
+use super::backend::{
+    MockBackend, MockChat, MockFriendship, MockLabel, MockMember, MockMessage, MockUser,
+};
+use crate::models::{
+    chat::{ChatMemberRole, ChatType, SystemMessageType},
+    friends::FriendshipStatus,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+// Идентификаторы фиксированы, чтобы токен из localStorage переживал перезагрузку страницы.
+pub const RYAN: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0001);
+pub const MARGOT: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0002);
+pub const KEANU: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0003);
+pub const EMMA: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0004);
+pub const CILLIAN: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0005);
+pub const ANA: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0006);
+pub const HARRISON: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0007);
+
+const KEANU_CHAT: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0001_0001);
+const EMMA_CHAT: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0001_0002);
+const DRIVE_CHAT: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0001_0003);
+
+fn seq_id(prefix: u128, n: u128) -> Uuid {
+    Uuid::from_u128(0x0000_0000_0000_4000_8000_0000_0000_0000 | (prefix << 32) | n)
+}
+
+fn member(n: u128, user_id: Uuid, role: ChatMemberRole) -> MockMember {
+    MockMember {
+        id: seq_id(2, n),
+        user_id,
+        member_name: None,
+        role,
+    }
+}
+
+fn message(n: u128, sender_id: Uuid, text: &str, created_at: NaiveDateTime) -> MockMessage {
+    MockMessage {
+        id: seq_id(3, n),
+        sender_id: Some(sender_id),
+        system_message_type: SystemMessageType::None,
+        message: text.to_string(),
+        is_edited: false,
+        is_pinned: false,
+        created_at,
+        updated_at: None,
+        pinned_at: None,
+    }
+}
+
+/// Демонстрационный набор данных. Вход: `ryan.gosling@gmail.com` / `realhero`,
+/// у остальных пользователей пароль `password`.
+pub fn seed(backend: &mut MockBackend) {
+    let now = Utc::now().naive_utc();
+
+    let people = [
+        (
+            RYAN,
+            "ryan_gosling",
+            "Ryan",
+            "Gosling",
+            true,
+            "I drive.",
+            "Актёр, музыкант и просто хороший человек.",
+        ),
+        (
+            MARGOT,
+            "margot_robbie",
+            "Margot",
+            "Robbie",
+            true,
+            "Hi Barbie!",
+            "Люблю розовый цвет.",
+        ),
+        (
+            KEANU,
+            "keanu_reeves",
+            "Keanu",
+            "Reeves",
+            false,
+            "You're breathtaking!",
+            "Немного знаю кунг-фу.",
+        ),
+        (
+            EMMA,
+            "emma_stone",
+            "Emma",
+            "Stone",
+            true,
+            "City of stars",
+            "Пою и танцую в Лос-Анджелесе.",
+        ),
+        (
+            CILLIAN,
+            "cillian_murphy",
+            "Cillian",
+            "Murphy",
+            false,
+            "By order of the Peaky Blinders",
+            "Физик-теоретик на полставки.",
+        ),
+        (
+            ANA,
+            "ana_de_armas",
+            "Ana",
+            "de Armas",
+            true,
+            "Interlinked",
+            "Голограмма с характером.",
+        ),
+        (
+            HARRISON,
+            "harrison_ford",
+            "Harrison",
+            "Ford",
+            false,
+            "It's not the age, it's the mileage",
+            "Пилот на пенсии.",
+        ),
+    ];
+    backend.users = people
+        .into_iter()
+        .map(
+            |(id, user_name, first_name, last_name, is_online, status, about)| MockUser {
+                id,
+                email: format!("{}@gmail.com", user_name.replace('_', ".")),
+                password: if id == RYAN { "realhero" } else { "password" }.to_string(),
+                user_name: user_name.to_string(),
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+                created_at: now - Duration::days(120),
+                last_login: now - Duration::hours(2),
+                is_online,
+                status: Some(status.to_string()),
+                about: Some(about.to_string()),
+            },
+        )
+        .collect();
+
+    backend.friendships = vec![
+        MockFriendship {
+            id: seq_id(1, 1),
+            requester_id: RYAN,
+            addressee_id: KEANU,
+            status: FriendshipStatus::Accepted,
+        },
+        MockFriendship {
+            id: seq_id(1, 2),
+            requester_id: EMMA,
+            addressee_id: RYAN,
+            status: FriendshipStatus::Accepted,
+        },
+        MockFriendship {
+            id: seq_id(1, 3),
+            requester_id: RYAN,
+            addressee_id: MARGOT,
+            status: FriendshipStatus::Accepted,
+        },
+        MockFriendship {
+            id: seq_id(1, 4),
+            requester_id: CILLIAN,
+            addressee_id: RYAN,
+            status: FriendshipStatus::Pending,
+        },
+        MockFriendship {
+            id: seq_id(1, 5),
+            requester_id: RYAN,
+            addressee_id: ANA,
+            status: FriendshipStatus::Pending,
+        },
+        MockFriendship {
+            id: seq_id(1, 6),
+            requester_id: KEANU,
+            addressee_id: EMMA,
+            status: FriendshipStatus::Accepted,
+        },
+    ];
+
+    backend.labels = vec![
+        MockLabel {
+            id: seq_id(4, 1),
+            owner_id: RYAN,
+            friendship_id: seq_id(1, 1),
+            label: "коллега".to_string(),
+        },
+        MockLabel {
+            id: seq_id(4, 2),
+            owner_id: RYAN,
+            friendship_id: seq_id(1, 2),
+            label: "La La Land".to_string(),
+        },
+    ];
+
+    let keanu_messages = vec![
+        message(1, KEANU, "Привет! Как съёмки?", now - Duration::days(2)),
+        message(
+            2,
+            RYAN,
+            "Отлично, почти закончили.",
+            now - Duration::days(2) + Duration::minutes(3),
+        ),
+        message(
+            3,
+            KEANU,
+            "Увидимся на премьере?",
+            now - Duration::minutes(40),
+        ),
+    ];
+
+    let emma_messages = vec![
+        message(4, EMMA, "Репетируем завтра в 10?", now - Duration::days(1)),
+        message(
+            5,
+            RYAN,
+            "Да, буду.",
+            now - Duration::days(1) + Duration::minutes(1),
+        ),
+    ];
+
+    let mut drive_messages = vec![MockMessage {
+        id: seq_id(3, 6),
+        sender_id: None,
+        system_message_type: SystemMessageType::ChatCreated,
+        message: "Ryan Gosling создал(а) чат «Drive»".to_string(),
+        is_edited: false,
+        is_pinned: false,
+        created_at: now - Duration::days(3),
+        updated_at: None,
+        pinned_at: None,
+    }];
+    let drive_lines = [
+        (RYAN, "Всем привет, это чат съёмочной группы."),
+        (EMMA, "Привет!"),
+        (KEANU, "Я тут просто в гостях."),
+        (RYAN, "Сбор в 7 утра на парковке."),
+        (EMMA, "Кто-нибудь видел мою куртку?"),
+        (KEANU, "Она в гримёрке."),
+    ];
+    for (i, (sender, text)) in drive_lines.iter().enumerate() {
+        let offset = Duration::hours(3) * (drive_lines.len() - i) as i32;
+        drive_messages.push(message(7 + i as u128, *sender, text, now - offset));
+    }
+    if let Some(pinned) = drive_messages.get_mut(4) {
+        pinned.is_pinned = true;
+        pinned.pinned_at = Some(now - Duration::hours(1));
+    }
+
+    backend.chats = vec![
+        MockChat {
+            id: KEANU_CHAT,
+            name: None,
+            chat_type: ChatType::Personal,
+            created_at: now - Duration::days(2),
+            members: vec![
+                member(1, RYAN, ChatMemberRole::Member),
+                member(2, KEANU, ChatMemberRole::Member),
+            ],
+            messages: keanu_messages,
+        },
+        MockChat {
+            id: EMMA_CHAT,
+            name: None,
+            chat_type: ChatType::Personal,
+            created_at: now - Duration::days(1),
+            members: vec![
+                member(3, RYAN, ChatMemberRole::Member),
+                member(4, EMMA, ChatMemberRole::Member),
+            ],
+            messages: emma_messages,
+        },
+        MockChat {
+            id: DRIVE_CHAT,
+            name: Some("Drive".to_string()),
+            chat_type: ChatType::Group,
+            created_at: now - Duration::days(3),
+            members: vec![
+                member(5, RYAN, ChatMemberRole::Owner),
+                member(6, EMMA, ChatMemberRole::Administrator),
+                member(7, KEANU, ChatMemberRole::Member),
+            ],
+            messages: drive_messages,
+        },
+    ];
+}
@@ -1,9 +1,17 @@
 pub mod auth;
 pub mod base;
+pub mod cache;
+pub mod cancel;
 pub mod chat;
 pub mod chat_members;
 pub mod chat_messages;
 pub mod error;
 pub mod friends;
 pub mod image;
+#[cfg(any(feature = "mock", debug_assertions))]
+pub mod mock;
+pub mod outbox;
 pub mod profile;
+pub mod realtime;
+pub mod retry;
+pub mod transport;
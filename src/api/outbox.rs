@@ -0,0 +1,450 @@
+use super::{
+    chat_messages::{CreateChatMessageRequest, create_chat_message, get_chat_messages},
+    error::ApiError,
+    realtime::{self, ConnectionStatus},
+    retry::{DEFAULT_TIMEOUT, RetryPolicy},
+};
+use crate::auth::use_auth;
+use crate::models::chat::{ChatMessage, WsMessage, WsMessageType};
+use crate::utils::{get_current_user_id, local_storage};
+use chrono::{NaiveDateTime, Utc};
+use leptos::{ev, prelude::*, task::spawn_local};
+use leptos_use::use_event_listener;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, time::Duration};
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use wasm_bindgen_futures::future_to_promise;
+
+const OUTBOX_KEY: &str = "outbox";
+
+/// Сколько раз подряд сервер может ответить 5xx, прежде чем сообщение
+/// перестанет задерживать очередь и будет ждать решения пользователя.
+const MAX_SERVER_ERRORS: u32 = 3;
+
+/// Сколько последних сообщений чата просматривать, проверяя, не дошла ли
+/// до сервера попытка, ответ на которую потерялся.
+const CONFIRM_PAGE_SIZE: u32 = 20;
+
+/// Допустимое расхождение часов клиента и сервера при такой проверке.
+const CLOCK_SKEW: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutboxState {
+    /// Ждёт связи или своей очереди.
+    Queued,
+    /// Запрос отправляет одна из вкладок. Если вкладку закрыли на полпути,
+    /// после `DEFAULT_TIMEOUT` сообщение снова считается ждущим.
+    Sending { since: NaiveDateTime },
+    /// Сервер отклонил сообщение, повторять без пользователя бессмысленно.
+    Failed { status: Option<u16> },
+}
+
+/// Сообщение, которое ещё не принял сервер.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntry {
+    pub id: Uuid,
+    pub request: CreateChatMessageRequest,
+    pub created_at: NaiveDateTime,
+    pub state: OutboxState,
+    /// Почему сервер отклонил сообщение, пока оно в состоянии `Failed`.
+    #[serde(default)]
+    pub error: Option<ApiError>,
+    /// Ответы 5xx подряд.
+    #[serde(default)]
+    server_errors: u32,
+    /// Ответ на прошлую попытку потерялся, и сообщение могло уже дойти.
+    #[serde(default)]
+    unconfirmed: bool,
+}
+
+impl OutboxEntry {
+    /// Относится ли сообщение к чату. Первое личное сообщение отправляется без чата,
+    /// по получателю, поэтому для личного чата передаётся собеседник.
+    pub fn is_for(&self, chat_id: Uuid, peer_id: Option<Uuid>) -> bool {
+        match self.request.chat_id {
+            Some(id) => id == chat_id,
+            None => peer_id.is_some() && self.request.receiver_id == peer_id,
+        }
+    }
+
+    fn is_stalled(&self, now: NaiveDateTime) -> bool {
+        match self.state {
+            OutboxState::Sending { since } => {
+                (now - since).to_std().unwrap_or_default() > DEFAULT_TIMEOUT
+            }
+            _ => false,
+        }
+    }
+
+    /// Запоминает неудачную попытку. Возвращает `true`, если сообщение стоит
+    /// повторить позже, и `false`, если решать теперь пользователю.
+    fn record_error(&mut self, error: ApiError) -> bool {
+        let retry_later = match &error {
+            ApiError::Network(_) => true,
+            // Запрос мог дойти до сервера. Вслепую не повторяем: перед следующей
+            // попыткой ищем сообщение в чате, а без чата искать негде
+            ApiError::Timeout | ApiError::Aborted => {
+                self.unconfirmed = self.request.chat_id.is_some();
+                self.unconfirmed
+            }
+            ApiError::Server(_) => {
+                self.server_errors += 1;
+                self.server_errors < MAX_SERVER_ERRORS
+            }
+            _ => false,
+        };
+        if retry_later {
+            self.state = OutboxState::Queued;
+        } else {
+            self.state = OutboxState::Failed {
+                status: error.status(),
+            };
+            self.error = Some(error);
+        }
+        retry_later
+    }
+}
+
+#[derive(Default)]
+struct Flush {
+    running: bool,
+    /// Неудачные попытки подряд, от них зависит пауза до следующей.
+    attempt: u32,
+    timer: Option<TimeoutHandle>,
+}
+
+thread_local! {
+    static ENTRIES: RwSignal<Vec<OutboxEntry>> = RwSignal::new(Vec::new());
+    static FLUSH: RefCell<Flush> = RefCell::new(Flush::default());
+}
+
+fn entries_signal() -> RwSignal<Vec<OutboxEntry>> {
+    ENTRIES.with(|entries| *entries)
+}
+
+/// Очередь текущего пользователя. Чтение подписывает на изменения.
+pub fn entries() -> Vec<OutboxEntry> {
+    entries_signal().get()
+}
+
+/// Состояние сообщения или `None`, если его уже нет в очереди.
+pub fn state(id: Uuid) -> Option<OutboxState> {
+    entries_signal().with(|entries| {
+        entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.state)
+    })
+}
+
+/// Ставит сообщение в очередь и сразу пытается отправить. Очередь хранится
+/// в localStorage, поэтому переживает перезагрузку и потерю связи.
+pub fn enqueue(request: CreateChatMessageRequest) -> Uuid {
+    let id = Uuid::new_v4();
+    modify(|entries| {
+        entries.push(OutboxEntry {
+            id,
+            request,
+            created_at: Utc::now().naive_utc(),
+            state: OutboxState::Queued,
+            error: None,
+            server_errors: 0,
+            unconfirmed: false,
+        })
+    });
+    flush();
+    id
+}
+
+/// Возвращает отклонённое сообщение в очередь.
+pub fn retry(id: Uuid) {
+    modify(|entries| {
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.id == id) {
+            entry.state = OutboxState::Queued;
+            entry.error = None;
+            entry.server_errors = 0;
+        }
+    });
+    flush();
+}
+
+/// Убирает сообщение из очереди в любом состоянии. Если его как раз
+/// отправляют, запрос не отменяется и сообщение может всё же дойти.
+pub fn discard(id: Uuid) {
+    modify(|entries| entries.retain(|entry| entry.id != id));
+}
+
+/// Загружает очередь пользователя и отправляет её, когда появляется связь.
+/// Вызывается из навигации, которая видна на всех страницах.
+pub fn track() {
+    let auth = use_auth();
+    Effect::new(move |_| {
+        auth.user_id();
+        reload();
+        flush();
+    });
+
+    Effect::new(move |_| {
+        if realtime::status() == ConnectionStatus::Online {
+            flush();
+        }
+    });
+
+    let _ = use_event_listener(window(), ev::online, |_| flush());
+    // Очередь общая для вкладок: подхватываем изменения, сделанные в соседних
+    let _ = use_event_listener(window(), ev::storage, |event| {
+        if event.key() == storage_key() {
+            reload();
+        }
+    });
+}
+
+/// Отправляет очередь по порядку. На временной ошибке останавливается и повторяет
+/// позже, отклонённое сервером сообщение оставляет пользователю и идёт дальше.
+fn flush() {
+    let already_running = FLUSH.with_borrow_mut(|flush| {
+        if let Some(timer) = flush.timer.take() {
+            timer.clear();
+        }
+        std::mem::replace(&mut flush.running, true)
+    });
+    if already_running {
+        return;
+    }
+    let Some(key) = storage_key() else {
+        FLUSH.with_borrow_mut(|flush| flush.running = false);
+        return;
+    };
+    // Чтение и запись localStorage между вкладками не атомарны: без блокировки
+    // две вкладки могут взять одно сообщение и отправить его дважды
+    if !with_lock(&key, drain()) {
+        spawn_local(drain());
+    }
+}
+
+/// Выполняет `task` под Web Lock `name`, общим для всех вкладок: пока её
+/// выполняет одна вкладка, остальные ждут. Возвращает `false`, если браузер
+/// Web Locks не поддерживает. В web-sys они пока за нестабильным флагом,
+/// поэтому `navigator.locks.request` вызываем сами.
+fn with_lock(name: &str, task: impl Future<Output = ()> + 'static) -> bool {
+    let navigator = window().navigator();
+    let Ok(locks) = js_sys::Reflect::get(&navigator, &"locks".into()) else {
+        return false;
+    };
+    let Some(request) = js_sys::Reflect::get(&locks, &"request".into())
+        .ok()
+        .and_then(|request| request.dyn_into::<js_sys::Function>().ok())
+    else {
+        return false;
+    };
+    // Блокировка держится, пока не выполнится промис из колбэка
+    let callback = Closure::once_into_js(move |_lock: JsValue| {
+        future_to_promise(async move {
+            task.await;
+            Ok(JsValue::UNDEFINED)
+        })
+    });
+    request.call2(&locks, &name.into(), &callback).is_ok()
+}
+
+async fn drain() {
+    while let Some(entry) = claim_next() {
+        match send(&entry).await {
+            Ok(message) => {
+                FLUSH.with_borrow_mut(|flush| flush.attempt = 0);
+                modify(|entries| entries.retain(|e| e.id != entry.id));
+                if let (Some(room_id), Ok(data)) =
+                    (entry.request.chat_id, serde_json::to_value(&message))
+                {
+                    realtime::deliver(WsMessage {
+                        room_id: Some(room_id),
+                        r#type: WsMessageType::ReceiveMessage,
+                        data,
+                    });
+                }
+            }
+            Err(error) => {
+                let retry_later = modify(|entries| {
+                    entries
+                        .iter_mut()
+                        .find(|e| e.id == entry.id)
+                        .is_some_and(|e| e.record_error(error))
+                });
+                if retry_later {
+                    schedule_retry();
+                    break;
+                }
+            }
+        }
+    }
+    FLUSH.with_borrow_mut(|flush| flush.running = false);
+}
+
+/// Отправляет сообщение. Если прошлая попытка могла дойти до сервера,
+/// сначала ищет его среди последних сообщений чата.
+async fn send(entry: &OutboxEntry) -> Result<ChatMessage, ApiError> {
+    if entry.unconfirmed
+        && let Some(chat_id) = entry.request.chat_id
+    {
+        let recent =
+            get_chat_messages(chat_id, None, Some(1), Some(CONFIRM_PAGE_SIZE), None).await?;
+        let user_id = get_current_user_id();
+        if let Some(message) = recent.into_iter().find(|message| {
+            message.sender_id.is_some()
+                && message.sender_id == user_id
+                && message.message == entry.request.message
+                && message.created_at >= entry.created_at - CLOCK_SKEW
+        }) {
+            return Ok(message);
+        }
+    }
+    create_chat_message(entry.request.clone()).await
+}
+
+/// Берёт первое ждущее сообщение. Если первое уже отправляет другая вкладка,
+/// ждём её, чтобы не нарушить порядок и не отправить дважды.
+fn claim_next() -> Option<OutboxEntry> {
+    let now = Utc::now().naive_utc();
+    modify(|entries| {
+        loop {
+            let entry = entries
+                .iter_mut()
+                .find(|entry| !matches!(entry.state, OutboxState::Failed { .. }))?;
+            if matches!(entry.state, OutboxState::Sending { .. }) {
+                if !entry.is_stalled(now) {
+                    return None;
+                }
+                // Вкладку закрыли посреди запроса: он мог дойти до сервера
+                if !entry.record_error(ApiError::Aborted) {
+                    continue;
+                }
+            }
+            entry.state = OutboxState::Sending { since: now };
+            return Some(entry.clone());
+        }
+    })
+}
+
+/// Паузы между попытками, пока связи нет.
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(60),
+        ..RetryPolicy::default()
+    }
+}
+
+fn schedule_retry() {
+    FLUSH.with_borrow_mut(|flush| {
+        let delay = retry_policy().backoff(flush.attempt);
+        flush.attempt = flush.attempt.saturating_add(1);
+        flush.timer = set_timeout_with_handle(flush_later, delay).ok();
+    });
+}
+
+fn flush_later() {
+    FLUSH.with_borrow_mut(|flush| flush.timer = None);
+    flush();
+}
+
+/// Хранилище — источник правды: каждое изменение перечитывает его,
+/// чтобы не затереть то, что записала соседняя вкладка.
+fn modify<T>(change: impl FnOnce(&mut Vec<OutboxEntry>) -> T) -> T {
+    let mut entries = load();
+    let result = change(&mut entries);
+    if let (Some(storage), Some(key)) = (local_storage(), storage_key())
+        && let Ok(value) = serde_json::to_string(&entries)
+    {
+        let _ = storage.set_item(&key, &value);
+    }
+    entries_signal().set(entries);
+    result
+}
+
+fn reload() {
+    entries_signal().set(load());
+}
+
+fn load() -> Vec<OutboxEntry> {
+    storage_key()
+        .and_then(|key| local_storage()?.get_item(&key).ok()?)
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+/// Очередь у каждого пользователя своя.
+fn storage_key() -> Option<String> {
+    get_current_user_id().map(|user_id| format!("{}:{}", OUTBOX_KEY, user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::error::ErrorDetails;
+
+    fn sending(chat_id: Option<Uuid>) -> OutboxEntry {
+        OutboxEntry {
+            id: Uuid::new_v4(),
+            request: CreateChatMessageRequest {
+                chat_id,
+                message: "Привет".to_string(),
+                receiver_id: chat_id.is_none().then(Uuid::new_v4),
+            },
+            created_at: Utc::now().naive_utc(),
+            state: OutboxState::Sending {
+                since: Utc::now().naive_utc(),
+            },
+            error: None,
+            server_errors: 0,
+            unconfirmed: false,
+        }
+    }
+
+    fn server_error() -> ApiError {
+        ApiError::Server(ErrorDetails {
+            status: 503,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn network_errors_keep_the_entry_queued() {
+        let mut entry = sending(Some(Uuid::new_v4()));
+        assert!(entry.record_error(ApiError::Network("offline".to_string())));
+        assert_eq!(entry.state, OutboxState::Queued);
+        assert!(!entry.unconfirmed);
+    }
+
+    #[test]
+    fn server_errors_fail_the_entry_after_the_limit() {
+        let mut entry = sending(Some(Uuid::new_v4()));
+        for _ in 1..MAX_SERVER_ERRORS {
+            assert!(entry.record_error(server_error()));
+        }
+        assert!(!entry.record_error(server_error()));
+        assert_eq!(entry.state, OutboxState::Failed { status: Some(503) });
+        assert_eq!(entry.error, Some(server_error()));
+    }
+
+    #[test]
+    fn unauthorized_fails_the_entry_at_once() {
+        let mut entry = sending(Some(Uuid::new_v4()));
+        assert!(!entry.record_error(ApiError::Unauthorized));
+        assert_eq!(entry.state, OutboxState::Failed { status: Some(401) });
+    }
+
+    #[test]
+    fn lost_responses_are_confirmed_before_resending() {
+        let mut entry = sending(Some(Uuid::new_v4()));
+        assert!(entry.record_error(ApiError::Timeout));
+        assert!(entry.unconfirmed);
+        assert_eq!(entry.state, OutboxState::Queued);
+
+        // Первое личное сообщение проверить негде, поэтому решает пользователь
+        let mut entry = sending(None);
+        assert!(!entry.record_error(ApiError::Aborted));
+        assert_eq!(entry.state, OutboxState::Failed { status: None });
+    }
+}
@@ -1,7 +1,10 @@
 use super::{base::ApiClient, error::ApiError};
 use crate::models::profile::Profile;
+use crate::sync::{SyncEvent, broadcast};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
+use web_sys::AbortSignal;
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -10,23 +13,35 @@ pub struct UpdateProfileRequest {
     pub new_about: Option<String>,
 }
 
-pub async fn get_current_user_profile() -> Result<Profile, ApiError> {
+const PROFILE_TTL: Duration = Duration::from_secs(30);
+
+pub async fn get_current_user_profile(abort: Option<AbortSignal>) -> Result<Profile, ApiError> {
     ApiClient::get("/profile/current-user-profile")
         .authenticated()
+        .abort_signal(abort)
+        .cached(PROFILE_TTL)
         .send_json()
         .await
 }
 
-pub async fn get_user_profile(user_id: Uuid) -> Result<Profile, ApiError> {
+pub async fn get_user_profile(
+    user_id: Uuid,
+    abort: Option<AbortSignal>,
+) -> Result<Profile, ApiError> {
     ApiClient::get(&format!("/profile/{}", user_id))
         .authenticated()
+        .abort_signal(abort)
+        .cached(PROFILE_TTL)
         .send_json()
         .await
 }
 
 pub async fn update_profile(request: UpdateProfileRequest) -> Result<Profile, ApiError> {
-    ApiClient::patch("/profile/update", &request)
+    let profile = ApiClient::patch("/profile/update", &request)
         .authenticated()
+        .invalidates("/profile")
         .send_json()
-        .await
+        .await?;
+    broadcast(SyncEvent::ProfileUpdated);
+    Ok(profile)
 }
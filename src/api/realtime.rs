@@ -0,0 +1,576 @@
+#[cfg(any(feature = "mock", debug_assertions))]
+use super::mock;
+use super::{chat_messages::get_chat_messages, retry::RetryPolicy};
+use crate::config::ws_room_url;
+use crate::models::chat::{ChatMessage, WsMessage, WsMessageType};
+use chrono::{NaiveDateTime, Utc};
+use leptos::prelude::*;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::Duration,
+};
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+/// Без подписчиков соединение комнаты держится ещё немного: при возврате
+/// в тот же чат не приходится переподключаться.
+const IDLE_CLOSE_DELAY: Duration = Duration::from_secs(30);
+
+/// Сколько последних сообщений комнаты запрашивать после разрыва.
+const REPLAY_PAGE_SIZE: u32 = 50;
+
+/// Сколько комнат фоновых подписок держат соединения одновременно.
+const MAX_BACKGROUND_ROOMS: usize = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    #[default]
+    Connecting,
+    Online,
+    Offline,
+}
+
+type Listener = Rc<dyn Fn(WsMessage)>;
+
+struct Subscription {
+    id: u64,
+    room_id: Uuid,
+    /// Место комнаты в очереди фоновой подписки, `None` у открытого на экране.
+    rank: Option<usize>,
+    listener: Listener,
+}
+
+/// Обработчики держим рядом с сокетом: пока они живы, браузер может их вызвать.
+struct Connection {
+    socket: WebSocket,
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl Connection {
+    fn close(self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+/// Соединение `/ws?roomId=` одной комнаты и всё, что нужно для его восстановления.
+#[derive(Default)]
+struct Room {
+    connection: Option<Connection>,
+    status: ConnectionStatus,
+    /// Неудачные попытки подряд, от них зависит пауза до переподключения.
+    attempt: u32,
+    timer: Option<TimeoutHandle>,
+    /// Когда пропало соединение, если оно уже было открыто.
+    offline_since: Option<NaiveDateTime>,
+    /// Время последнего полученного сообщения, для догрузки после разрыва.
+    last_seen: Option<NaiveDateTime>,
+    #[cfg(any(feature = "mock", debug_assertions))]
+    mock_subscription: Option<u64>,
+}
+
+impl Room {
+    fn clear_timer(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            timer.clear();
+        }
+    }
+
+    fn close(mut self) {
+        self.clear_timer();
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+        #[cfg(any(feature = "mock", debug_assertions))]
+        if let Some(subscription) = self.mock_subscription.take() {
+            mock::unsubscribe(subscription);
+        }
+    }
+}
+
+/// Сокет не описан в API_FRONTEND.md. LunkvayAPI шлёт события комнаты только
+/// в её собственное соединение `/ws?roomId=`, подписаться на несколько комнат
+/// одним соединением нельзя. Поэтому соединение открывается на каждую нужную комнату:
+/// открытые на экране подключаются всегда, а фоновые подписки списка чатов и
+/// счётчиков — только для `MAX_BACKGROUND_ROOMS` первых в своей очереди.
+/// Об остальных узнаём из периодического перезапроса списка чатов.
+#[derive(Default)]
+struct Realtime {
+    rooms: HashMap<Uuid, Room>,
+    subscriptions: Vec<Subscription>,
+    /// Комнаты, которым сейчас положено соединение.
+    joined: HashSet<Uuid>,
+    next_id: u64,
+}
+
+impl Realtime {
+    fn add(&mut self, room_id: Uuid, rank: Option<usize>, listener: Listener) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions.push(Subscription {
+            id,
+            room_id,
+            rank,
+            listener,
+        });
+        id
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.subscriptions
+            .retain(|subscription| subscription.id != id);
+    }
+
+    fn set_rank(&mut self, id: u64, rank: Option<usize>) {
+        if let Some(subscription) = self
+            .subscriptions
+            .iter_mut()
+            .find(|subscription| subscription.id == id)
+        {
+            subscription.rank = rank;
+        }
+    }
+
+    /// Комнаты, которым нужно соединение: открытые на экране и первые фоновые.
+    fn wanted(&self) -> HashSet<Uuid> {
+        let mut wanted: HashSet<Uuid> = self
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.rank.is_none())
+            .map(|subscription| subscription.room_id)
+            .collect();
+        let mut background: Vec<(usize, Uuid)> = self
+            .subscriptions
+            .iter()
+            .filter_map(|subscription| Some((subscription.rank?, subscription.room_id)))
+            .filter(|(_, room_id)| !wanted.contains(room_id))
+            .collect();
+        background.sort();
+        let mut picked = HashSet::new();
+        for (_, room_id) in background {
+            if picked.len() == MAX_BACKGROUND_ROOMS {
+                break;
+            }
+            picked.insert(room_id);
+        }
+        wanted.extend(picked);
+        wanted
+    }
+
+    /// Общее состояние для индикатора: худшее из состояний комнат.
+    fn status(&self) -> Option<ConnectionStatus> {
+        let statuses = self.rooms.values().map(|room| room.status);
+        if self.rooms.is_empty() {
+            None
+        } else if statuses.clone().any(|s| s == ConnectionStatus::Offline) {
+            Some(ConnectionStatus::Offline)
+        } else if statuses.clone().any(|s| s == ConnectionStatus::Connecting) {
+            Some(ConnectionStatus::Connecting)
+        } else {
+            Some(ConnectionStatus::Online)
+        }
+    }
+}
+
+thread_local! {
+    static REALTIME: RefCell<Realtime> = RefCell::new(Realtime::default());
+    static STATUS: RwSignal<ConnectionStatus> = RwSignal::new(ConnectionStatus::Connecting);
+}
+
+fn set_room_status(room_id: Uuid, status: ConnectionStatus) {
+    REALTIME.with_borrow_mut(|realtime| {
+        if let Some(room) = realtime.rooms.get_mut(&room_id) {
+            room.status = status;
+        }
+    });
+    publish_status();
+}
+
+/// Пересчитывает общее состояние. Сигнал обновляется, когда `REALTIME` уже отпущен.
+fn publish_status() {
+    let Some(status) = REALTIME.with_borrow(|realtime| realtime.status()) else {
+        return;
+    };
+    STATUS.with(|signal| {
+        if signal.get_untracked() != status {
+            signal.set(status);
+        }
+    });
+}
+
+/// Состояние соединения для индикатора в интерфейсе.
+pub fn status() -> ConnectionStatus {
+    STATUS.with(|signal| signal.get())
+}
+
+/// Открывает соединения комнатам, которым они теперь нужны, и отпускает остальные.
+fn rebalance() {
+    let (joins, leaves) = REALTIME.with_borrow_mut(|realtime| {
+        let wanted = realtime.wanted();
+        let joins: Vec<Uuid> = wanted.difference(&realtime.joined).copied().collect();
+        let leaves: Vec<Uuid> = realtime.joined.difference(&wanted).copied().collect();
+        realtime.joined = wanted;
+        (joins, leaves)
+    });
+    for room_id in joins {
+        join(room_id);
+    }
+    for room_id in leaves {
+        leave(room_id);
+    }
+}
+
+fn join(room_id: Uuid) {
+    // В офлайн-режиме события приходят от мок-бэкенда, а не из сокета.
+    #[cfg(any(feature = "mock", debug_assertions))]
+    if mock::is_enabled() {
+        REALTIME.with_borrow_mut(|realtime| {
+            let room = realtime.rooms.entry(room_id).or_default();
+            room.clear_timer();
+            room.mock_subscription.get_or_insert_with(|| {
+                mock::subscribe(room_id, move |mut message| {
+                    message.room_id = Some(room_id);
+                    receive(message);
+                })
+            });
+        });
+        set_room_status(room_id, ConnectionStatus::Online);
+        return;
+    }
+
+    let needs_connection = REALTIME.with_borrow_mut(|realtime| {
+        let room = realtime.rooms.entry(room_id).or_default();
+        room.clear_timer();
+        room.connection.is_none()
+    });
+    if needs_connection {
+        connect(room_id);
+    }
+}
+
+fn leave(room_id: Uuid) {
+    REALTIME.with_borrow_mut(|realtime| {
+        if let Some(room) = realtime.rooms.get_mut(&room_id) {
+            room.clear_timer();
+            room.timer =
+                set_timeout_with_handle(move || close_if_idle(room_id), IDLE_CLOSE_DELAY).ok();
+        }
+    });
+}
+
+fn close_if_idle(room_id: Uuid) {
+    let room = REALTIME.with_borrow_mut(|realtime| {
+        if let Some(room) = realtime.rooms.get_mut(&room_id) {
+            room.timer = None;
+        }
+        if realtime.joined.contains(&room_id) {
+            None
+        } else {
+            realtime.rooms.remove(&room_id)
+        }
+    });
+    if let Some(room) = room {
+        room.close();
+        publish_status();
+    }
+}
+
+fn connect(room_id: Uuid) {
+    set_room_status(room_id, ConnectionStatus::Connecting);
+    let Ok(socket) = WebSocket::new(&ws_room_url(room_id)) else {
+        schedule_reconnect(room_id);
+        return;
+    };
+
+    let on_open = Closure::<dyn FnMut()>::new(move || on_open(room_id));
+    // Сервер не пишет комнату в событие: она задана адресом соединения.
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        let message = event
+            .data()
+            .as_string()
+            .and_then(|text| serde_json::from_str::<WsMessage>(&text).ok());
+        if let Some(mut message) = message {
+            message.room_id = Some(room_id);
+            receive(message);
+        }
+    });
+    let closed = socket.clone();
+    let on_close =
+        Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| on_close(room_id, &closed));
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    let connection = Connection {
+        socket,
+        _on_open: on_open,
+        _on_message: on_message,
+        _on_close: on_close,
+    };
+    let unused = REALTIME.with_borrow_mut(|realtime| match realtime.rooms.get_mut(&room_id) {
+        Some(room) => room.connection.replace(connection),
+        None => Some(connection),
+    });
+    if let Some(connection) = unused {
+        connection.close();
+    }
+}
+
+fn on_open(room_id: Uuid) {
+    let offline_since = REALTIME.with_borrow_mut(|realtime| {
+        let room = realtime.rooms.get_mut(&room_id)?;
+        room.attempt = 0;
+        room.offline_since.take()
+    });
+    set_room_status(room_id, ConnectionStatus::Online);
+
+    if let Some(offline_since) = offline_since {
+        leptos::task::spawn_local(replay(room_id, offline_since));
+    }
+}
+
+fn on_close(room_id: Uuid, socket: &WebSocket) {
+    let should_reconnect = REALTIME.with_borrow_mut(|realtime| {
+        let is_joined = realtime.joined.contains(&room_id);
+        let Some(room) = realtime.rooms.get_mut(&room_id) else {
+            return false;
+        };
+        let is_current = room
+            .connection
+            .as_ref()
+            .is_some_and(|connection| connection.socket == *socket);
+        if !is_current {
+            return false;
+        }
+        // Обработчик выполняется прямо сейчас, поэтому освобождаем его позже.
+        let closed = room.connection.take();
+        set_timeout(move || drop(closed), Duration::ZERO);
+        if room.offline_since.is_none() && room.attempt == 0 {
+            room.offline_since = Some(Utc::now().naive_utc());
+        }
+        if !is_joined {
+            room.clear_timer();
+            realtime.rooms.remove(&room_id);
+        }
+        is_joined
+    });
+    if should_reconnect {
+        schedule_reconnect(room_id);
+    } else {
+        publish_status();
+    }
+}
+
+/// Паузы между попытками переподключения.
+fn reconnect_policy() -> RetryPolicy {
+    RetryPolicy {
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+        ..RetryPolicy::default()
+    }
+}
+
+fn schedule_reconnect(room_id: Uuid) {
+    set_room_status(room_id, ConnectionStatus::Offline);
+    REALTIME.with_borrow_mut(|realtime| {
+        let Some(room) = realtime.rooms.get_mut(&room_id) else {
+            return;
+        };
+        let delay = reconnect_policy().backoff(room.attempt);
+        room.attempt = room.attempt.saturating_add(1);
+        room.clear_timer();
+        room.timer = set_timeout_with_handle(
+            move || {
+                let still_needed = REALTIME.with_borrow_mut(|realtime| {
+                    let is_joined = realtime.joined.contains(&room_id);
+                    realtime.rooms.get_mut(&room_id).is_some_and(|room| {
+                        room.timer = None;
+                        room.connection.is_none() && is_joined
+                    })
+                });
+                if still_needed {
+                    connect(room_id);
+                }
+            },
+            delay,
+        )
+        .ok();
+    });
+}
+
+/// Догружает сообщения, пришедшие в комнату, пока соединения не было, и просит
+/// подписчиков перезапросить остальное: правки, удаления и закрепления за это
+/// время сокет уже не пришлёт.
+async fn replay(room_id: Uuid, offline_since: NaiveDateTime) {
+    if let Ok(recent) =
+        get_chat_messages(room_id, None, Some(1), Some(REPLAY_PAGE_SIZE), None).await
+    {
+        deliver_missed(room_id, recent, offline_since);
+    }
+    let resync = WsMessage {
+        room_id: Some(room_id),
+        r#type: WsMessageType::Resync,
+        data: serde_json::Value::Null,
+    };
+    set_timeout(move || deliver(resync), Duration::ZERO);
+}
+
+fn deliver_missed(room_id: Uuid, recent: Vec<ChatMessage>, offline_since: NaiveDateTime) {
+    let since = REALTIME.with_borrow(|realtime| {
+        realtime
+            .rooms
+            .get(&room_id)
+            .and_then(|room| room.last_seen)
+            .unwrap_or(offline_since)
+    });
+    // Страница идёт от новых к старым, а доставлять нужно по порядку.
+    for chat_message in recent.into_iter().rev() {
+        if chat_message.created_at <= since {
+            continue;
+        }
+        let Ok(data) = serde_json::to_value(&chat_message) else {
+            continue;
+        };
+        let message = WsMessage {
+            room_id: Some(room_id),
+            r#type: WsMessageType::ReceiveMessage,
+            data,
+        };
+        // Как и кадры сокета, каждое сообщение доставляется отдельной задачей.
+        set_timeout(move || receive(message), Duration::ZERO);
+    }
+}
+
+fn receive(message: WsMessage) {
+    let Some(room_id) = message.room_id else {
+        return;
+    };
+    REALTIME.with_borrow_mut(|realtime| {
+        if message.r#type == WsMessageType::ReceiveMessage
+            && let Some(room) = realtime.rooms.get_mut(&room_id)
+            && let Ok(chat_message) = serde_json::from_value::<ChatMessage>(message.data.clone())
+        {
+            room.last_seen = room.last_seen.max(Some(chat_message.created_at));
+        }
+    });
+    deliver(message);
+}
+
+/// Передаёт событие подписчикам комнаты так, будто его прислал сервер.
+/// Так клиент сам сообщает о подтверждённой отправке, не дожидаясь сокета.
+pub fn deliver(message: WsMessage) {
+    let Some(room_id) = message.room_id else {
+        return;
+    };
+    let listeners: Vec<Listener> = REALTIME.with_borrow(|realtime| {
+        realtime
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.room_id == room_id)
+            .map(|subscription| subscription.listener.clone())
+            .collect()
+    });
+    for listener in listeners {
+        listener(message.clone());
+    }
+}
+
+/// Подписки компонента на набор комнат. Снимаются при размонтировании.
+#[derive(Clone, Copy)]
+pub struct RoomSubscriptions {
+    ids: StoredValue<HashMap<Uuid, u64>, LocalStorage>,
+    background: bool,
+}
+
+impl RoomSubscriptions {
+    /// Подписки на комнаты, открытые на экране: соединение есть у каждой.
+    pub fn new() -> Self {
+        Self::with_background(false)
+    }
+
+    /// Подписки на все чаты пользователя, например для списка. Соединения
+    /// получают только первые `MAX_BACKGROUND_ROOMS` комнат в порядке `set`.
+    pub fn background() -> Self {
+        Self::with_background(true)
+    }
+
+    fn with_background(background: bool) -> Self {
+        let subscriptions = Self {
+            ids: StoredValue::new_local(HashMap::new()),
+            background,
+        };
+        on_cleanup(move || subscriptions.set(std::iter::empty(), |_| {}));
+        subscriptions
+    }
+
+    /// Оставляет подписки ровно на `rooms`: новые комнаты получают `listener`,
+    /// лишние отписываются.
+    pub fn set(
+        &self,
+        rooms: impl IntoIterator<Item = Uuid>,
+        listener: impl Fn(WsMessage) + Clone + 'static,
+    ) {
+        let rooms: Vec<Uuid> = rooms.into_iter().collect();
+        let background = self.background;
+        self.ids.try_update_value(|ids| {
+            REALTIME.with_borrow_mut(|realtime| {
+                ids.retain(|room_id, id| {
+                    let keep = rooms.contains(room_id);
+                    if !keep {
+                        realtime.remove(*id);
+                    }
+                    keep
+                });
+                for (index, room_id) in rooms.into_iter().enumerate() {
+                    let rank = background.then_some(index);
+                    match ids.get(&room_id) {
+                        Some(id) => realtime.set_rank(*id, rank),
+                        None => {
+                            let id = realtime.add(room_id, rank, Rc::new(listener.clone()));
+                            ids.insert(room_id, id);
+                        }
+                    }
+                }
+            });
+        });
+        rebalance();
+    }
+}
+
+impl Default for RoomSubscriptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn background_rooms_are_capped_in_rank_order() {
+        let mut realtime = Realtime::default();
+        for index in 0..MAX_BACKGROUND_ROOMS + 5 {
+            realtime.add(room(index as u128), Some(index), Rc::new(|_| {}));
+        }
+        let last = room((MAX_BACKGROUND_ROOMS + 4) as u128);
+        realtime.add(last, None, Rc::new(|_| {}));
+
+        let wanted = realtime.wanted();
+        assert_eq!(wanted.len(), MAX_BACKGROUND_ROOMS + 1);
+        assert!(wanted.contains(&last));
+        assert!(wanted.contains(&room(0)));
+        assert!(!wanted.contains(&room(MAX_BACKGROUND_ROOMS as u128)));
+    }
+}
@@ -0,0 +1,115 @@
+use super::transport::HttpResponse;
+use chrono::{DateTime, Utc};
+use futures::{
+    channel::oneshot,
+    future::{Either, select},
+};
+use std::{cell::RefCell, pin::pin, time::Duration};
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::AbortSignal;
+
+/// Ожидание ответа по умолчанию. Бесплатный хостинг бэкенда просыпается долго,
+/// поэтому запас большой, но запрос всё равно не висит бесконечно.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Статусы, при которых сервер обычно ещё не проснулся или перегружен.
+const RETRYABLE_STATUSES: [u16; 5] = [408, 429, 502, 503, 504];
+
+/// Сколько раз и с какими паузами повторять запрос при временных сбоях.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Всего попыток, включая первую.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Ожидание ответа на одну попытку.
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Одна попытка без повторов.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Пауза перед попыткой `attempt + 1`: экспоненциальный рост с джиттером,
+    /// чтобы вкладки не били в просыпающийся сервер одновременно.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        exponential.mul_f64(0.5 + js_sys::Math::random() * 0.5)
+    }
+
+    /// Пауза перед повтором после ответа сервера или `None`, если повторять не нужно.
+    /// `Retry-After` важнее собственного расчёта, но ждём не дольше `max_delay`.
+    pub fn delay_after(&self, response: &HttpResponse, attempt: u32) -> Option<Duration> {
+        if !RETRYABLE_STATUSES.contains(&response.status()) {
+            return None;
+        }
+        let delay = match response.header("Retry-After").and_then(parse_retry_after) {
+            Some(delay) => delay.min(self.max_delay),
+            None => self.backoff(attempt),
+        };
+        Some(delay)
+    }
+}
+
+/// `Retry-After` бывает числом секунд или HTTP-датой.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await
+}
+
+/// Пауза, которую обрывает отмена запроса. Возвращает `false`, если запрос отменили
+/// до её конца.
+pub async fn sleep_unless_aborted(duration: Duration, abort: Option<&AbortSignal>) -> bool {
+    let Some(abort) = abort else {
+        sleep(duration).await;
+        return true;
+    };
+    if abort.aborted() {
+        return false;
+    }
+    let (sender, aborted) = oneshot::channel::<()>();
+    let sender = RefCell::new(Some(sender));
+    let on_abort = Closure::<dyn Fn()>::new(move || {
+        if let Some(sender) = sender.borrow_mut().take() {
+            let _ = sender.send(());
+        }
+    });
+    let _ = abort.add_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+    let finished = matches!(
+        select(pin!(sleep(duration)), aborted).await,
+        Either::Left(_)
+    );
+    let _ = abort.remove_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+    finished
+}
@@ -0,0 +1,192 @@
+use super::error::ApiError;
+use crate::config::api_base_url;
+use leptos::prelude::set_timeout_with_handle;
+use reqwasm::http::{Method, Request};
+use serde::de::DeserializeOwned;
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc, time::Duration};
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{AbortController, AbortSignal, FormData};
+
+pub type TransportFuture = Pin<Box<dyn Future<Output = Result<HttpResponse, ApiError>>>>;
+
+/// Способ доставки запросов `ApiClient`. По умолчанию это `fetch` к настоящему API,
+/// но его можно подменить, например, на `mock::MockTransport`.
+pub trait Transport {
+    fn send(&self, request: HttpRequest) -> TransportFuture;
+}
+
+#[derive(Clone)]
+pub enum RequestBody {
+    Empty,
+    Json(String),
+    FormData(FormData),
+}
+
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub path: String,
+    pub token: Option<String>,
+    pub body: RequestBody,
+    pub timeout: Option<Duration>,
+    /// Сигнал отмены от вызывающего кода, см. `cancel::LatestRequest`.
+    pub abort: Option<AbortSignal>,
+}
+
+impl HttpRequest {
+    pub fn is_aborted(&self) -> bool {
+        self.abort.as_ref().is_some_and(|s| s.aborted())
+    }
+}
+
+/// Заголовки ответа, которые нужны клиенту. Остальные транспорт не копирует.
+/// API живёт на другом origin, поэтому браузер покажет `Retry-After`, только если
+/// сервер перечислил его в `Access-Control-Expose-Headers`. Иначе заголовка
+/// просто нет, и повтор ждёт обычный backoff.
+const RESPONSE_HEADERS: [&str; 1] = ["Retry-After"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    status: u16,
+    body: String,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.body
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        serde_json::from_str(&self.body).map_err(|e| ApiError::Parsing(e.to_string()))
+    }
+}
+
+pub struct FetchTransport;
+
+impl Transport for FetchTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture {
+        Box::pin(async move {
+            let url = format!("{}{}", api_base_url(), request.path);
+            // Один контроллер на запрос: его обрывает и таймаут, и отмена снаружи.
+            let controller = AbortController::new().ok();
+            let signal = controller.as_ref().map(|c| c.signal());
+            let timer = match (request.timeout, controller.clone()) {
+                (Some(timeout), Some(controller)) => {
+                    set_timeout_with_handle(move || controller.abort(), timeout).ok()
+                }
+                _ => None,
+            };
+            let external = request.abort;
+            let forward_abort = match (&external, controller) {
+                (Some(external), Some(controller)) => {
+                    if external.aborted() {
+                        controller.abort();
+                    }
+                    let on_abort = Closure::<dyn Fn()>::new(move || controller.abort());
+                    let _ = external.add_event_listener_with_callback(
+                        "abort",
+                        on_abort.as_ref().unchecked_ref(),
+                    );
+                    Some(on_abort)
+                }
+                _ => None,
+            };
+            let aborted = || signal.as_ref().is_some_and(|s| s.aborted());
+
+            let mut request_builder = Request::new(&url)
+                .method(request.method)
+                .abort_signal(signal.as_ref());
+
+            if let Some(token) = request.token {
+                request_builder =
+                    request_builder.header("Authorization", &format!("Bearer {}", token));
+            }
+
+            request_builder = match request.body {
+                RequestBody::Empty => request_builder,
+                RequestBody::Json(body) => request_builder
+                    .header("Content-Type", "application/json")
+                    .body(body),
+                RequestBody::FormData(body) => request_builder.body(body),
+            };
+
+            let result = async {
+                let response = request_builder
+                    .send()
+                    .await
+                    .map_err(|e| ApiError::Network(e.to_string()))?;
+                let body = response
+                    .text()
+                    .await
+                    .map_err(|e| ApiError::Parsing(e.to_string()))?;
+
+                let headers = response.headers();
+                let mut http_response = HttpResponse::new(response.status(), body);
+                for name in RESPONSE_HEADERS {
+                    if let Some(value) = headers.get(name) {
+                        http_response = http_response.with_header(name, value);
+                    }
+                }
+                Ok(http_response)
+            }
+            .await;
+
+            if let Some(timer) = timer {
+                timer.clear();
+            }
+            if let (Some(external), Some(on_abort)) = (&external, &forward_abort) {
+                let _ = external.remove_event_listener_with_callback(
+                    "abort",
+                    on_abort.as_ref().unchecked_ref(),
+                );
+            }
+            match result {
+                Err(_) if external.is_some_and(|s| s.aborted()) => Err(ApiError::Aborted),
+                Err(_) if aborted() => Err(ApiError::Timeout),
+                result => result,
+            }
+        })
+    }
+}
+
+thread_local! {
+    static TRANSPORT: RefCell<Rc<dyn Transport>> = RefCell::new(Rc::new(FetchTransport));
+}
+
+#[cfg_attr(not(any(feature = "mock", debug_assertions)), allow(dead_code))]
+pub fn set_transport(transport: impl Transport + 'static) {
+    TRANSPORT.with(|t| *t.borrow_mut() = Rc::new(transport));
+}
+
+pub fn transport() -> Rc<dyn Transport> {
+    TRANSPORT.with(|t| t.borrow().clone())
+}
@@ -1,11 +1,14 @@
-use crate::components::navigation::Navigation;
+use crate::auth::{AuthStatus, provide_auth_state, use_auth};
+use crate::components::{
+    navigation::Navigation, protected_route::ProtectedRoute, session_expired::SessionExpiredDialog,
+};
+use crate::config::router_base;
 use crate::pages::{
     chats::ChatsPage, friends::FriendsPage, login::LoginPage, profile::ProfilePage,
 };
-use crate::utils::{clear_token, has_token};
 use leptos::prelude::*;
 use leptos_router::components::*;
-use leptos_router::hooks::{use_location, use_navigate};
+use leptos_router::hooks::{use_location, use_navigate, use_query_map};
 use leptos_router::path;
 use stylance::import_style;
 
@@ -15,24 +18,34 @@ import_style!(style, "app.module.scss");
 fn MainLayout() -> impl IntoView {
     let location = use_location();
     let navigate = use_navigate();
+    let query = use_query_map();
+    let auth = use_auth();
+    // После входа возвращаем пользователя туда, где он был.
+    let redirect_target = move || {
+        query
+            .get_untracked()
+            .get("redirect")
+            .filter(|path| path.starts_with('/') && !path.starts_with("//"))
+            .unwrap_or_else(|| "/profile".to_string())
+    };
 
     let navigation_is_active = move || !location.pathname.get().ends_with("/auth");
 
-    // Следим за изменением пути и токена
+    // Следим за изменением пути и состояния входа
     Effect::new(move |_| {
         let pathname = location.pathname.get();
-        let token_present = has_token();
+        let status = auth.status();
 
-        match (token_present, pathname.as_str()) {
-            // пользователь вошёл, но находится на /auth → перенаправляем в профиль
-            (true, path) if path.ends_with("/auth") => navigate("/profile", Default::default()),
-            // пользователь вошёл и перешёл на /logout → очищаем токен и редиректим на /auth
-            (true, path) if path.ends_with("/logout") => {
-                clear_token();
+        match (status, pathname.as_str()) {
+            // пользователь вошёл, но находится на /auth → возвращаем туда, откуда пришёл
+            (AuthStatus::Authenticated, path) if path.ends_with("/auth") => {
+                navigate(&redirect_target(), Default::default())
+            }
+            // пользователь перешёл на /logout → выходим и редиректим на /auth
+            (_, path) if path.ends_with("/logout") => {
+                auth.sign_out();
                 navigate("/auth", Default::default());
             }
-            // пользователь не вошёл → не пускаем, если он не на /auth
-            (false, path) if !path.ends_with("/auth") => navigate("/auth", Default::default()),
             _ => {}
         }
     });
@@ -53,22 +66,26 @@ fn MainLayout() -> impl IntoView {
 
             <main class=main_class>
                 <Routes fallback=|| "Not found.">
-                    <Route path=path!("/") view=|| view! { <div/> }/>
+                    <Route path=path!("/") view=|| view! { <Redirect path="/profile"/> }/>
                     <Route path=path!("/auth") view=LoginPage/>
-                    <Route path=path!("/chats") view=ChatsPage/>
-                    <Route path=path!("/profile") view=ProfilePage/>
-                    <Route path=path!("/profile/:id") view=ProfilePage/>
-                    <Route path=path!("/friends") view=FriendsPage/>
+                    <Route path=path!("/chats") view=|| view! { <ProtectedRoute><ChatsPage/></ProtectedRoute> }/>
+                    <Route path=path!("/profile") view=|| view! { <ProtectedRoute><ProfilePage/></ProtectedRoute> }/>
+                    <Route path=path!("/profile/:id") view=|| view! { <ProtectedRoute><ProfilePage/></ProtectedRoute> }/>
+                    <Route path=path!("/friends") view=|| view! { <ProtectedRoute><FriendsPage/></ProtectedRoute> }/>
                 </Routes>
             </main>
+
+            <SessionExpiredDialog/>
         </div>
     }
 }
 
 #[component]
 pub fn App() -> impl IntoView {
+    provide_auth_state();
+
     view! {
-        <Router base="/lunkvay-rs">
+        <Router base=router_base()>
             <MainLayout />
         </Router>
     }
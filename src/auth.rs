@@ -0,0 +1,153 @@
+use crate::sync::{SyncEvent, broadcast};
+use crate::utils::{Claims, clear_token, decode_claims, get_token, store_token, token_received_at};
+use chrono::{Duration, Utc};
+use leptos::prelude::*;
+use uuid::Uuid;
+
+/// Запас до истечения, после которого токен уже не отправляем.
+const EXPIRY_LEEWAY_SECS: i64 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthStatus {
+    Anonymous,
+    Authenticated,
+    /// Сессия закончилась сама (по `exp` или 401), пользователь ещё не ушёл на вход.
+    Expired,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub token: String,
+    pub claims: Claims,
+    pub received_at: Option<i64>,
+}
+
+impl Session {
+    fn load() -> Option<Self> {
+        let token = get_token()?;
+        let claims = decode_claims(&token)?;
+        Some(Self {
+            token,
+            claims,
+            received_at: token_received_at(),
+        })
+    }
+
+    /// Сколько осталось до истечения. `None`, если в токене нет `exp`.
+    pub fn expires_in(&self) -> Option<Duration> {
+        let expires_at = self.claims.expires_at(self.received_at)?;
+        Some(Duration::seconds(
+            expires_at - Utc::now().timestamp() - EXPIRY_LEEWAY_SECS,
+        ))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_in()
+            .is_some_and(|left| left <= Duration::zero())
+    }
+}
+
+/// Состояние входа для всего приложения. Его обновляют `LoginPage`, выход
+/// и `ApiClient` (на 401 и истёкший токен), а подписываются маршруты и компоненты.
+#[derive(Clone, Copy)]
+pub struct AuthState {
+    session: RwSignal<Option<Session>>,
+    status: RwSignal<AuthStatus>,
+}
+
+thread_local! {
+    static AUTH_STATE: AuthState = AuthState::load();
+}
+
+impl AuthState {
+    fn load() -> Self {
+        let session = Session::load();
+        let status = if session.is_some() {
+            AuthStatus::Authenticated
+        } else {
+            AuthStatus::Anonymous
+        };
+        Self {
+            session: RwSignal::new(session),
+            status: RwSignal::new(status),
+        }
+    }
+
+    pub fn status(&self) -> AuthStatus {
+        self.status.get()
+    }
+
+    pub fn session(&self) -> Option<Session> {
+        self.session.get()
+    }
+
+    pub fn session_untracked(&self) -> Option<Session> {
+        self.session.get_untracked()
+    }
+
+    pub fn user_id(&self) -> Option<Uuid> {
+        self.session
+            .with(|session| session.as_ref().map(|s| s.claims.id))
+    }
+
+    pub fn user_id_untracked(&self) -> Option<Uuid> {
+        self.session
+            .with_untracked(|session| session.as_ref().map(|s| s.claims.id))
+    }
+
+    pub fn sign_in(&self, token: &str) {
+        store_token(token);
+        self.reload(AuthStatus::Authenticated);
+        broadcast(SyncEvent::SignedIn);
+    }
+
+    pub fn sign_out(&self) {
+        clear_token();
+        self.reload(AuthStatus::Anonymous);
+        broadcast(SyncEvent::SignedOut);
+    }
+
+    /// Завершает истёкшую сессию и показывает диалог повторного входа.
+    pub fn expire(&self) {
+        clear_token();
+        self.reload(AuthStatus::Expired);
+        broadcast(SyncEvent::SessionExpired);
+    }
+
+    /// Перечитывает токен из хранилища, например после входа в другой вкладке.
+    /// Если токена нет, `status` `Authenticated` превращается в `Anonymous`.
+    pub fn reload(&self, status: AuthStatus) {
+        let session = Session::load();
+        let status = match (&session, status) {
+            (Some(_), _) => AuthStatus::Authenticated,
+            (None, AuthStatus::Authenticated) => AuthStatus::Anonymous,
+            (None, status) => status,
+        };
+        self.session.set(session);
+        self.status.set(status);
+    }
+
+    /// Пользователь увидел диалог истёкшей сессии и идёт на вход.
+    pub fn acknowledge_expired(&self) {
+        if self.status.get_untracked() == AuthStatus::Expired {
+            self.status.set(AuthStatus::Anonymous);
+        }
+    }
+}
+
+pub fn auth_state() -> AuthState {
+    AUTH_STATE.with(|state| *state)
+}
+
+pub fn provide_auth_state() {
+    provide_context(auth_state());
+}
+
+pub fn use_auth() -> AuthState {
+    use_context::<AuthState>().unwrap_or_else(auth_state)
+}
+
+/// Путь к странице входа с возвратом на `path` после входа.
+pub fn login_path(path: &str) -> String {
+    format!("/auth?redirect={}", js_sys::encode_uri_component(path))
+}
@@ -1,13 +1,18 @@
 use crate::{
     api::{
+        cache,
+        cancel::LatestRequest,
         chat_members::{
-            CreateChatMemberRequest, DeleteChatMemberRequest, UpdateChatMemberRequest,
-            create_chat_member, delete_chat_member, get_chat_members, update_chat_member,
+            CreateChatMemberRequest, DeleteChatMemberRequest, MemberChange,
+            UpdateChatMemberRequest, chat_members_path, create_chat_member, delete_chat_member,
+            get_chat_members, update_chat_member,
         },
+        error::ApiError,
         friends::get_friends,
+        realtime::RoomSubscriptions,
     },
-    models::chat::{ChatMember, ChatMemberRole},
-    utils::{API_BASE_URL, get_current_user_id},
+    models::chat::{ChatMember, ChatMemberRole, WsMessageType},
+    utils::{avatar_url, get_current_user_id},
 };
 use leptos::prelude::*;
 use stylance::import_style;
@@ -21,6 +26,7 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
     let show_edit_member_modal = RwSignal::new(None::<ChatMember>);
     let selected_role = RwSignal::new(ChatMemberRole::Member);
     let new_member_name = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
 
     let current_user_id = get_current_user_id().unwrap_or_default();
 
@@ -29,20 +35,38 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
             move || async move { get_friends(None, Some(50)).await.unwrap_or_default() },
         );
 
-    let chat_members =
-        LocalResource::new(
-            move || async move { get_chat_members(chat_id).await.unwrap_or_default() },
-        );
+    let members_request = LatestRequest::new();
+    let chat_members = LocalResource::new(move || {
+        let abort = members_request.next();
+        async move { get_chat_members(chat_id, abort).await.unwrap_or_default() }
+    });
+
+    // Роли, имена и исключения от других участников приходят через сокет
+    RoomSubscriptions::new().set([chat_id], move |msg| {
+        if msg.r#type == WsMessageType::Resync {
+            cache::invalidate(&chat_members_path(chat_id));
+            chat_members.refetch();
+            return;
+        }
+        if let Some(change) = MemberChange::receive(&msg) {
+            chat_members.update(|m| {
+                if let Some(members) = m.as_mut() {
+                    change.apply(members);
+                }
+            });
+        }
+    });
 
     let create_member_action = Action::new_local(move |req: &CreateChatMemberRequest| {
         let req = req.clone();
         async move {
-            if let Ok(new_member) = create_chat_member(req).await {
-                chat_members.update(|m| {
+            match create_chat_member(req).await {
+                Ok(new_member) => chat_members.update(|m| {
                     if let Some(members) = m.as_mut() {
                         members.push(new_member)
                     }
-                });
+                }),
+                Err(e) => error.set(Some(member_error_message(&e))),
             }
         }
     });
@@ -50,17 +74,17 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
     let update_member_action = Action::new_local(move |req: &UpdateChatMemberRequest| {
         let req = req.clone();
         async move {
-            if let Ok(updated_member) = update_chat_member(req).await {
-                chat_members.update(|m| {
-                    if let Some(members) = m.as_mut() {
-                        if let Some(member) = members
+            match update_chat_member(req).await {
+                Ok(updated_member) => chat_members.update(|m| {
+                    if let Some(members) = m.as_mut()
+                        && let Some(member) = members
                             .iter_mut()
                             .find(|mem| mem.user_id == updated_member.user_id)
-                        {
-                            *member = updated_member;
-                        }
+                    {
+                        *member = updated_member;
                     }
-                });
+                }),
+                Err(e) => error.set(Some(member_error_message(&e))),
             }
         }
     });
@@ -68,12 +92,13 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
     let delete_member_action = Action::new_local(move |req: &DeleteChatMemberRequest| {
         let req = req.clone();
         async move {
-            if delete_chat_member(req.clone()).await.is_ok() {
-                chat_members.update(|m| {
+            match delete_chat_member(req.clone()).await {
+                Ok(_) => chat_members.update(|m| {
                     if let Some(members) = m.as_mut() {
                         members.retain(|mem| mem.user_id != req.member_id)
                     }
-                });
+                }),
+                Err(e) => error.set(Some(member_error_message(&e))),
             }
         }
     });
@@ -84,7 +109,7 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
             .unwrap_or_default()
             .iter()
             .find(|m| m.user_id == current_user_id)
-            .map(|m| m.role.clone())
+            .map(|m| m.role)
     });
 
     view! {
@@ -110,33 +135,39 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
                     </button>
                 </div>
             </div>
+            <Show when=move || error.get().is_some()>
+                <p class=style::error_message on:click=move |_| error.set(None)>
+                    {move || error.get().unwrap_or_default()}
+                </p>
+            </Show>
             <ul class=style::members_list>
                 <Suspense>
                     <For
                         each=move || chat_members.get().unwrap_or_default()
-                        key=|member| member.id
+                        key=|member| (member.id, member.member_name.clone(), member.role)
                         children=move |member| {
                             let member_clone = RwSignal::new(member.clone());
-                            let current_role = current_user_role.get_untracked().unwrap_or_default();
+                            // Права пересчитываются, если роль текущего пользователя меняется на лету
+                            let current_role = move || current_user_role.get().unwrap_or_default();
 
                             let can_edit_role = Memo::new(move |_|
-                                current_role == ChatMemberRole::Owner && member.user_id != current_user_id
+                                current_role() == ChatMemberRole::Owner && member.user_id != current_user_id
                             );
 
                             let can_edit_name = Memo::new(move |_|
                                 member.user_id == current_user_id ||
-                                current_role == ChatMemberRole::Owner ||
-                                (current_role == ChatMemberRole::Administrator && member.role != ChatMemberRole::Owner)
+                                current_role() == ChatMemberRole::Owner ||
+                                (current_role() == ChatMemberRole::Administrator && member.role != ChatMemberRole::Owner)
                             );
 
                             let can_delete = Memo::new(move |_|
-                                (current_role == ChatMemberRole::Owner && member.user_id != current_user_id) ||
-                                (current_role == ChatMemberRole::Administrator && member.role != ChatMemberRole::Owner && member.role != ChatMemberRole::Administrator)
+                                (current_role() == ChatMemberRole::Owner && member.user_id != current_user_id) ||
+                                (current_role() == ChatMemberRole::Administrator && member.role != ChatMemberRole::Owner && member.role != ChatMemberRole::Administrator)
                             );
 
                             view! {
                                 <li class=style::member_item>
-                                    <img class=style::avatar src=format!("{}/avatar/{}", API_BASE_URL, member.user_id) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
+                                    <img class=style::avatar src=avatar_url(member.user_id) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
                                     <div class=style::member_info>
                                         <p class=style::member_name>{member.member_name.clone().unwrap_or_else(|| format!("{} {}", member.first_name, member.last_name))}</p>
                                         <p class=style::member_username>{format!("@{}", member.user_name)}</p>
@@ -188,7 +219,7 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
                                 let user_id = friend.user_id;
                                 view! {
                                     <li class=style::member_item>
-                                        <img class=style::avatar src=format!("{}/avatar/{}", API_BASE_URL, user_id) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
+                                        <img class=style::avatar src=avatar_url(user_id) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
                                         <div class=style::member_info>
                                             <p class=style::member_name>{format!("{} {}", friend.first_name, friend.last_name)}</p>
                                             <p class=style::member_username>{format!("@{}", friend.user_name)}</p>
@@ -282,3 +313,11 @@ pub fn ChatMembersPanel(chat_id: Uuid, show: ReadSignal<bool>) -> impl IntoView
         </div>
     }
 }
+
+fn member_error_message(error: &ApiError) -> String {
+    error.localized(&[
+        (403, "Недостаточно прав для этого действия"),
+        (404, "Участник или чат не найден"),
+        (409, "Пользователь уже состоит в чате"),
+    ])
+}
@@ -3,7 +3,7 @@ use crate::api::{
     image::{delete_chat_image, upload_chat_image},
 };
 use crate::models::chat::Chat;
-use crate::utils::{API_BASE_URL, get_current_user_id};
+use crate::utils::chat_image_url;
 use leptos::prelude::*;
 use stylance::import_style;
 use web_sys::SubmitEvent;
@@ -75,12 +75,11 @@ pub fn ChatSettingsWindow(
         if let Some(input) = ev
             .target()
             .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            && let Some(file) = input.files().and_then(|list| list.get(0))
         {
-            if let Some(file) = input.files().and_then(|list| list.get(0)) {
-                let url = web_sys::Url::create_object_url_with_blob(&file).unwrap();
-                preview_image_url.set(Some(url));
-                selected_file.set(Some(file));
-            }
+            let url = web_sys::Url::create_object_url_with_blob(&file).unwrap();
+            preview_image_url.set(Some(url));
+            selected_file.set(Some(file));
         }
     };
 
@@ -91,7 +90,7 @@ pub fn ChatSettingsWindow(
                 <form on:submit=on_submit>
                     <div class=style::image_upload_section>
                         <div class=style::image_preview>
-                            <img src=move || preview_image_url.get().unwrap_or_else(|| format!("{}/chat-image/{}/{}?v={}", API_BASE_URL, get_current_user_id().unwrap_or_default(),chat_id, avatar_count.get())) onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
+                            <img src=move || preview_image_url.get().unwrap_or_else(|| chat_image_url(chat_id, avatar_count.get())) onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
                         </div>
                         <input type="file" accept="image/*" on:change=on_file_change class=style::file_input/>
                         <button type="button" on:click=move |_| {delete_chat_image_action.dispatch(());} class=style::delete_image_button>"Удалить изображение чата"</button>
@@ -0,0 +1,88 @@
+use crate::config::max_message_length;
+use leptos::html::Textarea;
+use leptos::prelude::*;
+use stylance::import_style;
+
+import_style!(style, "composer.module.scss");
+
+/// За сколько символов до предела показывать счётчик.
+const COUNTER_MARGIN: usize = 500;
+
+/// Можно ли отправить текст: не пустой и не длиннее лимита сервера.
+pub fn can_send(text: &str) -> bool {
+    !text.trim().is_empty() && text.chars().count() <= max_message_length()
+}
+
+/// Многострочное поле сообщения: Enter отправляет, Shift+Enter переносит строку,
+/// Escape вызывает `on_cancel`. Высота растёт вместе с текстом до предела из стилей.
+/// `reserved` — сколько символов приложение добавит к тексту при отправке,
+/// они тоже входят в лимит и счётчик.
+#[component]
+pub fn Composer(
+    value: RwSignal<String>,
+    on_submit: Callback<()>,
+    #[prop(optional)] on_cancel: Option<Callback<()>>,
+    #[prop(into)] placeholder: String,
+    #[prop(optional)] autofocus: bool,
+    #[prop(optional, into)] reserved: Signal<usize>,
+) -> impl IntoView {
+    let textarea_ref = NodeRef::<Textarea>::new();
+    let length = Memo::new(move |_| value.with(|text| text.chars().count()) + reserved.get());
+
+    //EFFECTS
+    Effect::new(move |_| {
+        value.track();
+        if let Some(textarea) = textarea_ref.get() {
+            request_animation_frame(move || {
+                let _ = textarea.set_attribute("style", "height: auto");
+                let height = textarea.scroll_height();
+                let _ = textarea.set_attribute("style", &format!("height: {}px", height));
+            });
+        }
+    });
+
+    Effect::new(move |_| {
+        if autofocus && let Some(textarea) = textarea_ref.get() {
+            let _ = textarea.focus();
+        }
+    });
+
+    //VIEW
+    view! {
+        <div class=style::composer>
+            <textarea
+                class=style::textarea
+                rows="1"
+                placeholder=placeholder
+                node_ref=textarea_ref
+                bind:value=value
+                on:keydown=move |ev| {
+                    // Во время набора через IME Enter подтверждает ввод, а не отправляет
+                    if ev.key() == "Enter" && !ev.shift_key() && !ev.is_composing() {
+                        ev.prevent_default();
+                        if value.with_untracked(|text| !text.trim().is_empty())
+                            && length.get_untracked() <= max_message_length()
+                        {
+                            on_submit.run(());
+                        }
+                    } else if ev.key() == "Escape"
+                        && let Some(on_cancel) = on_cancel
+                    {
+                        on_cancel.run(());
+                    }
+                }
+            ></textarea>
+            <Show when=move || { length.get() >= max_message_length().saturating_sub(COUNTER_MARGIN) }>
+                <span class=move || {
+                    if length.get() > max_message_length() {
+                        format!("{} {}", style::counter, style::counter_exceeded)
+                    } else {
+                        style::counter.to_string()
+                    }
+                }>
+                    {move || format!("{}/{}", length.get(), max_message_length())}
+                </span>
+            </Show>
+        </div>
+    }
+}
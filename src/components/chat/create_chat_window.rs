@@ -2,7 +2,7 @@ use crate::api::{
     chat::{CreateGroupChatRequest, create_group_chat},
     friends::get_friends,
 };
-use crate::utils::API_BASE_URL;
+use crate::utils::avatar_url;
 use leptos::prelude::*;
 use stylance::import_style;
 use uuid::Uuid;
@@ -30,11 +30,9 @@ pub fn CreateChatWindow(
 
     // EFFECTS
     Effect::new(move |_| {
-        if create_chat_action.version().get() > 0 {
-            if create_chat_action.value().get().is_some() {
-                refetch_chats.run(());
-                set_show_create_chat_window.set(false);
-            }
+        if create_chat_action.version().get() > 0 && create_chat_action.value().get().is_some() {
+            refetch_chats.run(());
+            set_show_create_chat_window.set(false);
         }
     });
 
@@ -87,7 +85,7 @@ pub fn CreateChatWindow(
                                                     friends.into_iter().map(|friendship| {
                                                         let friend_id = friendship.user_id;
                                                         let is_selected = move || selected_friends.get().contains(&friend_id);
-                                                        let avatar_url = format!("{}/avatar/{}", API_BASE_URL, friend_id);
+                                                        let avatar_url = avatar_url(friend_id);
                                                         view! {
                                                             <div
                                                                 class=move || format!("{} {}", style::friend_item, if is_selected() { style::selected } else { "" })
@@ -0,0 +1,27 @@
+use super::reply;
+
+/// Как и ответ, пересылка передаётся первой строкой текста: `[forwarded:<автор>]`.
+pub(super) const FORWARD_PREFIX: &str = "[forwarded:";
+
+pub fn encode(from: &str, text: &str) -> String {
+    // Перенос строки в имени оборвал бы служебную строку
+    let from = from.replace('\n', " ");
+    format!(
+        "{}{}]\n{}",
+        FORWARD_PREFIX,
+        from.trim(),
+        reply::escape(text)
+    )
+}
+
+/// Автор пересланного сообщения и текст без служебной строки.
+pub fn split(message: &str) -> (Option<&str>, &str) {
+    let parsed = message.strip_prefix(FORWARD_PREFIX).and_then(|rest| {
+        let (header, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        Some((header.strip_suffix(']')?, body))
+    });
+    match parsed {
+        Some((from, body)) => (Some(from), body),
+        None => (None, message),
+    }
+}
@@ -0,0 +1,234 @@
+use super::composer::can_send;
+use crate::api::{
+    chat::get_all_chats,
+    chat_members::get_chat_members,
+    chat_messages::{CreateChatMessageRequest, create_chat_message},
+    friends::get_friends,
+    realtime,
+};
+use crate::models::chat::{ChatType, WsMessage, WsMessageType};
+use crate::utils::{avatar_url, chat_image_url, get_current_user_id};
+use futures::{StreamExt, stream};
+use leptos::prelude::*;
+use stylance::import_style;
+use uuid::Uuid;
+
+import_style!(style, "forward_window.module.scss");
+
+/// Сколько списков участников личных чатов запрашивать одновременно.
+const MEMBERS_CONCURRENCY: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ForwardTarget {
+    Chat(Uuid),
+    Friend(Uuid),
+}
+
+impl ForwardTarget {
+    fn request(self, message: String) -> CreateChatMessageRequest {
+        match self {
+            ForwardTarget::Chat(chat_id) => CreateChatMessageRequest {
+                chat_id: Some(chat_id),
+                message,
+                receiver_id: None,
+            },
+            ForwardTarget::Friend(receiver_id) => CreateChatMessageRequest {
+                chat_id: None,
+                message,
+                receiver_id: Some(receiver_id),
+            },
+        }
+    }
+}
+
+/// Выбор чатов и друзей, куда переслать уже подготовленный текст `message`.
+#[component]
+pub fn ForwardWindow(message: String, on_close: Callback<()>) -> impl IntoView {
+    // SIGNALS
+    let selected = RwSignal::new(Vec::<ForwardTarget>::new());
+    // Служебная строка пересылки удлиняет текст, и он может перестать помещаться
+    let fits = can_send(&message);
+    let error =
+        RwSignal::new((!fits).then(|| "Сообщение слишком длинное для пересылки".to_string()));
+
+    // RESOURCES
+    let chats_res = LocalResource::new(move || get_all_chats(None));
+    let friends_res = LocalResource::new(move || get_friends(None, Some(100)));
+
+    // Друга с личным чатом не показываем дважды. Участников личного чата список
+    // чатов не отдаёт: собеседника видно по последнему сообщению, если его написал он,
+    // а участников остальных личных чатов запрашиваем по несколько за раз
+    let personal_peers_res = LocalResource::new(move || async move {
+        let Ok(chats) = get_all_chats(None).await else {
+            return Vec::new();
+        };
+        let user_id = get_current_user_id();
+        let mut peers = Vec::new();
+        let mut unknown = Vec::new();
+        for chat in chats
+            .iter()
+            .filter(|chat| chat.chat_type == ChatType::Personal)
+        {
+            match chat
+                .last_message
+                .as_ref()
+                .and_then(|message| message.sender_id)
+                .filter(|sender_id| Some(*sender_id) != user_id)
+            {
+                Some(peer) => peers.push(peer),
+                None => unknown.push(chat.id),
+            }
+        }
+        let members = stream::iter(unknown)
+            .map(|chat_id| get_chat_members(chat_id, None))
+            .buffer_unordered(MEMBERS_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        peers.extend(
+            members
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|member| member.user_id)
+                .filter(|id| Some(*id) != user_id),
+        );
+        peers
+    });
+
+    // ACTIONS
+    let forward_action = Action::new_local(move |targets: &Vec<ForwardTarget>| {
+        let targets = targets.clone();
+        let message = message.clone();
+        async move {
+            let mut failed = Vec::new();
+            for target in targets {
+                match create_chat_message(target.request(message.clone())).await {
+                    Ok(sent) => {
+                        if let (ForwardTarget::Chat(room_id), Ok(data)) =
+                            (target, serde_json::to_value(&sent))
+                        {
+                            realtime::deliver(WsMessage {
+                                room_id: Some(room_id),
+                                r#type: WsMessageType::ReceiveMessage,
+                                data,
+                            });
+                        }
+                    }
+                    Err(_) => failed.push(target),
+                }
+            }
+            failed
+        }
+    });
+
+    // EFFECTS
+    Effect::new(move |_| {
+        if let Some(failed) = forward_action.value().get() {
+            if failed.is_empty() {
+                on_close.run(());
+            } else {
+                error.set(Some(format!(
+                    "Не удалось переслать в {} из {}. Попробуйте ещё раз.",
+                    failed.len(),
+                    selected.get_untracked().len()
+                )));
+                selected.set(failed);
+            }
+        }
+    });
+
+    // EVENT HANDLERS
+    let toggle = move |target: ForwardTarget| {
+        selected.update(|selected| {
+            if selected.contains(&target) {
+                selected.retain(|t| *t != target);
+            } else {
+                selected.push(target);
+            }
+        });
+    };
+
+    let on_forward = move |_| {
+        let targets = selected.get_untracked();
+        if fits && !targets.is_empty() && !forward_action.pending().get_untracked() {
+            error.set(None);
+            forward_action.dispatch(targets);
+        }
+    };
+
+    let item_class = move |target: ForwardTarget| {
+        move || {
+            if selected.get().contains(&target) {
+                format!("{} {}", style::item, style::selected)
+            } else {
+                style::item.to_string()
+            }
+        }
+    };
+
+    view! {
+        <div class=style::backdrop on:click=move |_| on_close.run(())>
+            <div class=style::content on:click=|e| e.stop_propagation()>
+                <h2>"Переслать сообщение"</h2>
+                <div class=style::list>
+                    <Suspense fallback=|| view! { <p>"Загрузка..."</p> }>
+                        <span class=style::section_title>"Чаты"</span>
+                        {move || chats_res.get().map(|chats| match chats {
+                            Ok(chats) if chats.is_empty() => view! { <p>"Чатов пока нет."</p> }.into_any(),
+                            Ok(chats) => chats.into_iter().map(|chat| {
+                                let target = ForwardTarget::Chat(chat.id);
+                                view! {
+                                    <div class=item_class(target) on:click=move |_| toggle(target)>
+                                        <img src=chat_image_url(chat.id, 0) onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
+                                        <span>{chat.name.unwrap_or_default()}</span>
+                                    </div>
+                                }
+                            }).collect_view().into_any(),
+                            Err(e) => view! { <p>"Ошибка загрузки чатов: "{e.to_string()}</p> }.into_any(),
+                        })}
+                        <span class=style::section_title>"Друзья"</span>
+                        {move || friends_res.get().map(|friends| match friends {
+                            Ok(friends) => {
+                                let peers = personal_peers_res.get().unwrap_or_default();
+                                let friends = friends
+                                    .into_iter()
+                                    .filter(|f| !peers.contains(&f.user_id))
+                                    .collect::<Vec<_>>();
+                                if friends.is_empty() {
+                                    view! { <p>"Все друзья уже есть в списке чатов."</p> }.into_any()
+                                } else {
+                                    friends.into_iter().map(|friendship| {
+                                        let target = ForwardTarget::Friend(friendship.user_id);
+                                        view! {
+                                            <div class=item_class(target) on:click=move |_| toggle(target)>
+                                                <img src=avatar_url(friendship.user_id) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
+                                                <span>{format!("{} {}", friendship.first_name, friendship.last_name)}</span>
+                                            </div>
+                                        }
+                                    }).collect_view().into_any()
+                                }
+                            }
+                            Err(e) => view! { <p>"Ошибка загрузки друзей: "{e.to_string()}</p> }.into_any(),
+                        })}
+                    </Suspense>
+                </div>
+
+                {move || error.get().map(|error| view! { <p class=style::error>{error}</p> })}
+
+                <div class=style::form_actions>
+                    <button
+                        class=style::submit_button
+                        disabled=move || !fits || selected.get().is_empty() || forward_action.pending().get()
+                        on:click=on_forward
+                    >
+                        {move || match selected.get().len() {
+                            0 => "Переслать".to_string(),
+                            n => format!("Переслать ({})", n),
+                        }}
+                    </button>
+                    <button class=style::cancel_button on:click=move |_| on_close.run(())>"Отмена"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1,34 +1,364 @@
 use crate::{
-    api::{chat_members::get_chat_members, chat_messages::*},
+    api::{
+        cache,
+        cancel::LatestRequest,
+        chat_members::{MemberChange, chat_members_path, get_chat_members},
+        chat_messages::*,
+        outbox::{self, OutboxEntry, OutboxState},
+        realtime::{self, ConnectionStatus, RoomSubscriptions},
+    },
     components::{
-        chat::{chat_members_panel::ChatMembersPanel, chat_settings_window::ChatSettingsWindow},
+        chat::{
+            chat_members_panel::ChatMembersPanel,
+            chat_settings_window::ChatSettingsWindow,
+            composer::{Composer, can_send},
+            forward,
+            forward_window::ForwardWindow,
+            reply,
+            virtual_list::{ListLayout, ScrollAnchor},
+        },
+        rich_text::{RichText, contains_match},
         spinner::Spinner,
     },
+    drafts,
     models::chat::{
         Chat, ChatMemberRole, ChatMessage, ChatType, PinnedMessageData, SystemMessageType,
         WsMessage, WsMessageType,
     },
-    utils::{API_BASE_URL, DOMAIN, get_current_user_id},
+    unread,
+    utils::{avatar_url, chat_image_url, get_current_user_id},
 };
-use chrono::{NaiveDate, Utc};
-use codee::string::JsonSerdeCodec;
-use leptos::html::Div;
-use leptos::{ev, prelude::*};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use gloo_timers::future::TimeoutFuture;
+use leptos::html::{Div, Input};
+use leptos::{ev, prelude::*, task::spawn_local};
 use leptos_use::{
-    UseInfiniteScrollOptions, UseTimeoutFnReturn, UseWebSocketReturn, use_event_listener,
-    use_infinite_scroll_with_options, use_timeout_fn, use_websocket,
+    UseInfiniteScrollOptions, UseTimeoutFnReturn, use_event_listener,
+    use_infinite_scroll_with_options, use_timeout_fn,
 };
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::time::Duration;
 use stylance::import_style;
 use uuid::Uuid;
+use web_sys::{Element, HtmlDivElement, wasm_bindgen::JsCast};
 
 import_style!(style, "messages.module.scss");
 
 const PAGE_SIZE: u32 = 20;
+/// Сколько последних закреплённых перебирает баннер над сообщениями.
+const PINNED_BANNER_SIZE: u32 = 5;
+/// Сколько последних сообщений сверяется после восстановления соединения.
+const RESYNC_LIMIT: u32 = 100;
+/// Сообщения одного автора с паузой не больше этой собираются в одну группу.
+const GROUP_INTERVAL: TimeDelta = TimeDelta::minutes(5);
 
 #[derive(Clone, Debug, PartialEq)]
 enum ListItem {
-    Message(ChatMessage),
+    Message(ChatMessage, GroupPosition),
     DateSeparator(NaiveDate),
+    UnreadDivider,
+}
+
+/// Место сообщения в группе подряд идущих сообщений одного автора:
+/// аватар и имя показываются у первого, отступ после группы — у последнего.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct GroupPosition {
+    starts_group: bool,
+    ends_group: bool,
+}
+
+/// Расставляет положения в группах. Группу прерывают разделители дат и
+/// непрочитанных, системные сообщения, смена автора и пауза дольше `GROUP_INTERVAL`.
+fn group_messages(items: &mut [ListItem]) {
+    let mut previous: Option<usize> = None;
+    for index in 0..items.len() {
+        let ListItem::Message(msg, _) = &items[index] else {
+            previous = None;
+            continue;
+        };
+        let continues = previous.is_some_and(|previous| match &items[previous] {
+            ListItem::Message(prev, _) => continues_group(prev, msg),
+            _ => false,
+        });
+        if let Some(previous) = previous
+            && let ListItem::Message(_, position) = &mut items[previous]
+        {
+            position.ends_group = !continues;
+        }
+        if let ListItem::Message(_, position) = &mut items[index] {
+            *position = GroupPosition {
+                starts_group: !continues,
+                ends_group: true,
+            };
+        }
+        previous = Some(index);
+    }
+}
+
+fn continues_group(previous: &ChatMessage, msg: &ChatMessage) -> bool {
+    previous.system_message_type == SystemMessageType::None
+        && msg.system_message_type == SystemMessageType::None
+        && previous.sender_id.is_some()
+        && previous.sender_id == msg.sender_id
+        && (msg.created_at - previous.created_at).abs() <= GROUP_INTERVAL
+}
+
+impl ListItem {
+    /// Постоянный ключ элемента, по нему запоминается измеренная высота.
+    fn key(&self) -> String {
+        match self {
+            ListItem::Message(msg, _) => msg.id.to_string(),
+            ListItem::DateSeparator(date) => format!("date:{}", date),
+            ListItem::UnreadDivider => "unread".to_string(),
+        }
+    }
+
+    /// Высота до первого измерения.
+    fn estimated_height(&self) -> f64 {
+        match self {
+            ListItem::Message(..) => 80.0,
+            ListItem::DateSeparator(_) => 60.0,
+            ListItem::UnreadDivider => 40.0,
+        }
+    }
+}
+
+/// Элементы списка вместе с их раскладкой. Список меняется по частям: старые
+/// страницы добавляются в начало, новые сообщения в конец, а группы и
+/// разделители пересчитываются только рядом с изменением.
+#[derive(Default)]
+struct MessageList {
+    items: Vec<ListItem>,
+    /// Сколько первых элементов относятся к загруженным сообщениям, дальше очередь отправки
+    loaded: usize,
+    pending: Vec<ChatMessage>,
+    /// Измеренные высоты по ключу элемента, они переживают уход элемента из списка
+    heights: HashMap<String, f64>,
+    layout: ListLayout,
+}
+
+impl MessageList {
+    /// Заменяет загруженные сообщения, например первой страницей чата.
+    fn reset(&mut self, messages: Vec<ChatMessage>) {
+        self.splice(0..self.loaded, Vec::new());
+        self.loaded = 0;
+        self.append(messages);
+    }
+
+    /// Добавляет в начало более старые сообщения, от старых к новым.
+    fn prepend(&mut self, older: Vec<ChatMessage>) {
+        let last_date = older.last().map(|msg| msg.created_at.date());
+        let items = with_dates(older, None);
+        let count = items.len();
+        // День, на котором заканчивается страница, уже начат разделителем
+        let removed = match self.items.first() {
+            Some(ListItem::DateSeparator(date)) if Some(*date) == last_date => 1,
+            _ => 0,
+        };
+        self.splice(0..removed, items);
+        self.loaded = self.loaded + count - removed;
+        self.regroup(0..count + 1);
+    }
+
+    /// Добавляет новые сообщения после загруженных, перед очередью отправки.
+    fn append(&mut self, newer: Vec<ChatMessage>) {
+        let items = with_dates(newer, self.last_loaded_date());
+        let start = self.loaded;
+        let count = items.len();
+        self.splice(start..start, items);
+        self.loaded += count;
+        self.regroup(start.saturating_sub(1)..start + count);
+        self.refresh_pending();
+    }
+
+    /// Загруженные сообщения от старых к новым, без очереди отправки.
+    fn messages(&self) -> impl DoubleEndedIterator<Item = &ChatMessage> {
+        self.items[..self.loaded]
+            .iter()
+            .filter_map(|item| match item {
+                ListItem::Message(msg, _) => Some(msg),
+                _ => None,
+            })
+    }
+
+    fn message(&self, message_id: Uuid) -> Option<&ChatMessage> {
+        match &self.items[self.position(message_id)?] {
+            ListItem::Message(msg, _) => Some(msg),
+            _ => None,
+        }
+    }
+
+    fn contains(&self, message_id: Uuid) -> bool {
+        self.position(message_id).is_some()
+    }
+
+    /// Меняет сообщение на его месте.
+    fn modify(&mut self, message_id: Uuid, change: impl FnOnce(&mut ChatMessage)) {
+        if let Some(index) = self.position(message_id)
+            && let ListItem::Message(msg, _) = &mut self.items[index]
+        {
+            change(msg);
+        }
+    }
+
+    fn remove(&mut self, message_id: Uuid) {
+        let Some(index) = self.position(message_id) else {
+            return;
+        };
+        self.splice(index..index + 1, Vec::new());
+        self.loaded -= 1;
+        // Без последнего сообщения дня разделитель даты не нужен
+        let day_start = self.items[..index]
+            .iter()
+            .rposition(|item| matches!(item, ListItem::DateSeparator(_)));
+        let day_end = self.items[index..self.loaded]
+            .iter()
+            .position(|item| matches!(item, ListItem::DateSeparator(_)))
+            .map_or(self.loaded, |end| index + end);
+        if let Some(day_start) = day_start
+            && !self.items[day_start..day_end]
+                .iter()
+                .any(|item| matches!(item, ListItem::Message(..)))
+        {
+            self.splice(day_start..day_start + 1, Vec::new());
+            self.loaded -= 1;
+        }
+        self.regroup(index.saturating_sub(1)..index);
+        self.refresh_pending();
+    }
+
+    /// Сверяет загруженные сообщения с последними с сервера, от старых к новым:
+    /// подменяет изменённые и убирает удалённые. Старше самого раннего из `recent`
+    /// сверять не с чем, если только `complete` не говорит, что это весь чат.
+    fn sync(&mut self, recent: Vec<ChatMessage>, complete: bool) {
+        let oldest = recent.first().map(|msg| msg.created_at);
+        let deleted = self
+            .messages()
+            .filter(|msg| complete || oldest.is_some_and(|oldest| msg.created_at > oldest))
+            .filter(|msg| !recent.iter().any(|fresh| fresh.id == msg.id))
+            .map(|msg| msg.id)
+            .collect::<Vec<_>>();
+        for message_id in deleted {
+            self.remove(message_id);
+        }
+        let newest = self.messages().next_back().map(|msg| msg.created_at);
+        let mut newer = Vec::new();
+        for fresh in recent {
+            if self.contains(fresh.id) {
+                self.modify(fresh.id, |msg| *msg = fresh);
+            } else if newest.is_none_or(|newest| fresh.created_at > newest) {
+                newer.push(fresh);
+            }
+        }
+        if !newer.is_empty() {
+            self.append(newer);
+        }
+    }
+
+    /// Ставит разделитель непрочитанных перед сообщением `first_unread`.
+    fn set_unread_divider(&mut self, first_unread: Option<Uuid>) {
+        if let Some(index) = self.items[..self.loaded]
+            .iter()
+            .position(|item| *item == ListItem::UnreadDivider)
+        {
+            self.splice(index..index + 1, Vec::new());
+            self.loaded -= 1;
+            self.regroup(index.saturating_sub(1)..index);
+        }
+        if let Some(index) = first_unread.and_then(|id| self.position(id)) {
+            self.splice(index..index, vec![ListItem::UnreadDivider]);
+            self.loaded += 1;
+            self.regroup(index..index + 1);
+        }
+    }
+
+    /// Неотправленные всегда внизу, в порядке очереди.
+    fn set_pending(&mut self, pending: Vec<ChatMessage>) {
+        self.pending = pending;
+        self.refresh_pending();
+    }
+
+    fn set_heights(&mut self, changed: Vec<(String, f64)>) {
+        let indices = changed
+            .iter()
+            .filter_map(|(key, height)| Some((self.layout.index_of(key)?, *height)))
+            .collect::<Vec<_>>();
+        self.layout.set_heights(indices);
+        self.heights.extend(changed);
+    }
+
+    fn refresh_pending(&mut self) {
+        let items = with_dates(self.pending.clone(), self.last_loaded_date());
+        let start = self.loaded;
+        self.splice(start..self.items.len(), items);
+        self.regroup(start.saturating_sub(1)..self.items.len());
+    }
+
+    fn position(&self, message_id: Uuid) -> Option<usize> {
+        self.items[..self.loaded]
+            .iter()
+            .position(|item| matches!(item, ListItem::Message(msg, _) if msg.id == message_id))
+    }
+
+    fn last_loaded_date(&self) -> Option<NaiveDate> {
+        self.items[..self.loaded]
+            .iter()
+            .rev()
+            .find_map(|item| match item {
+                ListItem::DateSeparator(date) => Some(*date),
+                _ => None,
+            })
+    }
+
+    /// Заменяет элементы вместе с их местом в раскладке.
+    fn splice(&mut self, range: Range<usize>, items: Vec<ListItem>) {
+        let sizes = items
+            .iter()
+            .map(|item| {
+                let key = item.key();
+                let height = self
+                    .heights
+                    .get(&key)
+                    .copied()
+                    .unwrap_or_else(|| item.estimated_height());
+                (key, height)
+            })
+            .collect::<Vec<_>>();
+        self.layout.splice(range.clone(), sizes);
+        self.items.splice(range, items);
+    }
+
+    /// Пересчитывает группы, которые задевает `range`. Группы не переходят
+    /// через разделители, поэтому хватает участка между ближайшими из них.
+    fn regroup(&mut self, range: Range<usize>) {
+        let is_separator = |item: &ListItem| !matches!(item, ListItem::Message(..));
+        let end = range.end.min(self.items.len());
+        let start = self.items[..range.start.min(end)]
+            .iter()
+            .rposition(is_separator)
+            .unwrap_or(0);
+        let end = self.items[end..]
+            .iter()
+            .position(is_separator)
+            .map_or(self.items.len(), |offset| end + offset);
+        group_messages(&mut self.items[start..end]);
+    }
+}
+
+/// Элементы для сообщений по порядку: перед первым сообщением каждого нового дня
+/// ставится разделитель. `last_date` — день, которым заканчивается список перед ними.
+fn with_dates(messages: Vec<ChatMessage>, mut last_date: Option<NaiveDate>) -> Vec<ListItem> {
+    let mut items = Vec::with_capacity(messages.len());
+    for msg in messages {
+        let msg_date = msg.created_at.date();
+        if last_date != Some(msg_date) {
+            items.push(ListItem::DateSeparator(msg_date));
+            last_date = Some(msg_date);
+        }
+        items.push(ListItem::Message(msg, GroupPosition::default()));
+    }
+    items
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -36,6 +366,9 @@ struct ContextMenuState {
     message_id: Uuid,
     is_my_message: bool,
     is_pinned: bool,
+    is_forwarded: bool,
+    /// Сообщение ещё в очереди отправки: его можно только убрать
+    is_pending: bool,
     x: i32,
     y: i32,
 }
@@ -50,32 +383,57 @@ pub fn Messages(
     let chat_id = chat.id;
     let chat_cloned = chat.clone();
 
-    let UseWebSocketReturn { message, .. } = use_websocket::<(), WsMessage, JsonSerdeCodec>(
-        &format!("wss://{}/ws?roomId={}", DOMAIN, chat_id),
-    );
+    // События комнаты приходят через соединение комнаты в `realtime`
+    let ws_message = RwSignal::new(None::<WsMessage>);
+    RoomSubscriptions::new().set([chat_id], move |msg| ws_message.set(Some(msg)));
+
+    // Отметку прочтения запоминаем до открытия: по ней ставится разделитель новых сообщений
+    let last_read_at = unread::last_read(chat_id);
+    unread::open_chat(chat_id);
+    on_cleanup(move || unread::close_chat(chat_id));
 
     //SIGNALS
-    let messsage_input = RwSignal::new(String::new());
-    let messages = RwSignal::new(Vec::<ChatMessage>::new());
+    let messsage_input = RwSignal::new(drafts::get_untracked(chat_id).unwrap_or_default());
     let current_page = RwSignal::new(1);
     let has_more_messages = RwSignal::new(true);
     let messages_area_ref = NodeRef::<Div>::new();
+    let message_list_ref = NodeRef::<Div>::new();
+    // Отрисовываемый список с разделителями и высотами, положение прокрутки относительно него
+    let list = RwSignal::new(MessageList::default());
+    let scroll_position = RwSignal::new((0.0, 0.0));
+    let scroll_anchor = StoredValue::new(ScrollAnchor::Bottom);
     let is_loading_more = RwSignal::new(false);
     let context_menu_state: RwSignal<Option<ContextMenuState>> = RwSignal::new(None);
     let editing_message_id: RwSignal<Option<Uuid>> = RwSignal::new(None);
     let edit_input = RwSignal::new(String::new());
+    // Полный список закреплённых открыт
     let show_pinned = RwSignal::new(false);
+    let pinned_list = RwSignal::new(Vec::<ChatMessage>::new());
+    let pinned_page = RwSignal::new(1);
+    let has_more_pinned = RwSignal::new(false);
+    let is_loading_pinned = RwSignal::new(false);
+    // Какое из закреплённых сейчас показывает баннер
+    let pinned_index = RwSignal::new(0usize);
+    let replying_to: RwSignal<Option<ChatMessage>> = RwSignal::new(None);
+    // Готовый текст пересылаемого сообщения, пока открыто окно выбора получателей
+    let forwarding: RwSignal<Option<String>> = RwSignal::new(None);
+    let show_search = RwSignal::new(false);
+    let search_query = RwSignal::new(String::new());
+    let search_current: RwSignal<Option<Uuid>> = RwSignal::new(None);
+    let is_searching_older = RwSignal::new(false);
+    let search_input_ref = NodeRef::<Input>::new();
+    // Исходное сообщение, к которому перешли из цитаты ответа
+    let highlighted: RwSignal<Option<Uuid>> = RwSignal::new(None);
+    // Непрочитанные на момент открытия: после отметки прочтения и до последнего загруженного
+    let unread_range = RwSignal::new(None::<(Option<NaiveDateTime>, NaiveDateTime)>);
+    let show_jump_to_unread = RwSignal::new(true);
+    // Сообщения из очереди отправки, которые уже вернулись из комнаты раньше ответа сервера
+    let echoed = RwSignal::new(HashSet::<Uuid>::new());
     let (is_panel_open, set_is_panel_open) = signal(false);
     let (render_panel, set_render_panel) = signal(false);
     let (animate_panel, set_animate_panel) = signal(false);
     let (show_chat_settings_window, set_show_chat_settings_window) = signal(false);
-    let chat_image = format!(
-        "{}/chat-image/{}/{}?v={}",
-        API_BASE_URL,
-        get_current_user_id().unwrap_or_default(),
-        chat_id,
-        avatar_count.get()
-    );
+    let chat_image = chat_image_url(chat_id, avatar_count.get());
 
     let UseTimeoutFnReturn {
         start: start_unmount_timer,
@@ -107,19 +465,42 @@ pub fn Messages(
     });
 
     //RESOURCES
-    let initial_messages = LocalResource::new(move || async move {
-        get_chat_messages(chat_id, None, Some(1), Some(PAGE_SIZE))
-            .await
-            .unwrap_or_default()
+    // Запросы отменяются при переключении чата, чтобы старые ответы не попали в новый.
+    let messages_request = LatestRequest::new();
+    let initial_messages = LocalResource::new(move || {
+        let abort = messages_request.next();
+        async move {
+            get_chat_messages(chat_id, None, Some(1), Some(PAGE_SIZE), abort)
+                .await
+                .unwrap_or_default()
+        }
     });
 
-    let pinned_messages = LocalResource::new(move || async move {
-        get_chat_messages(chat_id, Some(true), Some(1), Some(5))
+    let pinned_request = LatestRequest::new();
+    let pinned_messages = LocalResource::new(move || {
+        let abort = pinned_request.next();
+        async move {
+            get_chat_messages(
+                chat_id,
+                Some(true),
+                Some(1),
+                Some(PINNED_BANNER_SIZE),
+                abort,
+            )
             .await
             .unwrap_or_default()
+        }
     });
 
-    let chat_members = LocalResource::new(move || async move { get_chat_members(chat_id).await });
+    let members_request = LatestRequest::new();
+    let chat_members = LocalResource::new(move || {
+        let abort = members_request.next();
+        async move { get_chat_members(chat_id, abort).await }
+    });
+
+    let older_messages_request = LatestRequest::new();
+    let resync_request = LatestRequest::new();
+    let pinned_list_request = LatestRequest::new();
 
     let current_user_role = Memo::new(move |_| {
         if let Some(Ok(members)) = chat_members.get() {
@@ -134,12 +515,33 @@ pub fn Messages(
 
     let is_member = Memo::new(move |_| current_user_role.get().is_some());
 
-    //ACTIONS
-    let send_message = Action::new_local(move |input: &CreateChatMessageRequest| {
-        let input = input.clone();
-        async move { create_chat_message(input).await }
+    // Собеседник в личном чате: первое сообщение ему уходит по получателю, без чата
+    let is_personal = chat.chat_type == ChatType::Personal;
+    let peer_id = Memo::new(move |_| {
+        if !is_personal {
+            return None;
+        }
+        let me = get_current_user_id();
+        chat_members.with(|members| match members {
+            Some(Ok(members)) => members
+                .iter()
+                .find(|m| Some(m.user_id) != me)
+                .map(|m| m.user_id),
+            _ => None,
+        })
     });
 
+    let pending_messages = Memo::new(move |_| {
+        let peer_id = peer_id.get();
+        echoed.with(|echoed| {
+            outbox::entries()
+                .into_iter()
+                .filter(|entry| entry.is_for(chat_id, peer_id) && !echoed.contains(&entry.id))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    //ACTIONS
     let delete_message_action = Action::new_local(move |message_id: &Uuid| {
         let message_id = *message_id;
         async move {
@@ -178,6 +580,41 @@ pub fn Messages(
             }
         });
 
+    // Первая страница заменяет список закреплённых, следующие дописываются в конец
+    let load_pinned_page = move |page: u32| {
+        is_loading_pinned.set(true);
+        spawn_local(async move {
+            let result = get_chat_messages(
+                chat_id,
+                Some(true),
+                Some(page),
+                Some(PAGE_SIZE),
+                pinned_list_request.next(),
+            )
+            .await;
+            if let Ok(pinned) = result {
+                has_more_pinned.try_set(pinned.len() == PAGE_SIZE as usize);
+                pinned_page.try_set(page);
+                pinned_list.try_update(|list| {
+                    if page == 1 {
+                        list.clear();
+                    }
+                    list.extend(pinned);
+                });
+            }
+            is_loading_pinned.try_set(false);
+        });
+    };
+
+    let current_pin = Memo::new(move |_| {
+        let pinned = pinned_messages.get()?;
+        if pinned.is_empty() {
+            return None;
+        }
+        let index = pinned_index.get() % pinned.len();
+        Some((index, pinned.len(), pinned[index].clone()))
+    });
+
     //EFFECTS
     Effect::new(move |_| {
         if let Some(mut initial) = initial_messages.get() {
@@ -185,7 +622,13 @@ pub fn Messages(
                 has_more_messages.set(false);
             }
             initial.reverse();
-            messages.set(initial);
+            if let Some(last) = initial.last().map(|m| m.created_at) {
+                if initial.iter().any(|m| unread::is_unread(m, last_read_at)) {
+                    unread_range.set(Some((last_read_at, last)));
+                }
+                unread::mark_read(chat_id, last);
+            }
+            list.update(|list| list.reset(initial));
             if let Some(element) = messages_area_ref.get() {
                 request_animation_frame(move || {
                     element.set_scroll_top(element.scroll_height());
@@ -195,7 +638,7 @@ pub fn Messages(
     });
 
     Effect::new(move |_| {
-        if let Some(ws_message) = message.get() {
+        if let Some(ws_message) = ws_message.get() {
             match ws_message.r#type {
                 WsMessageType::ReceiveMessage => {
                     if let Ok(mut chat_message) =
@@ -208,7 +651,27 @@ pub fn Messages(
                             let should_scroll = messages_area.scroll_top()
                                 + messages_area.client_height()
                                 >= messages_area.scroll_height() - 200;
-                            messages.update(|msgs| msgs.push(chat_message));
+                            // После разрыва сообщение может прийти повторно при догрузке
+                            if list.with_untracked(|list| list.contains(chat_message.id)) {
+                                return;
+                            }
+                            // Своё сообщение может прийти раньше ответа на запрос отправки
+                            if chat_message.is_my_message
+                                && let Some(entry) = pending_messages.with_untracked(|pending| {
+                                    pending
+                                        .iter()
+                                        .find(|entry| {
+                                            !matches!(entry.state, OutboxState::Failed { .. })
+                                                && entry.request.message == chat_message.message
+                                        })
+                                        .map(|entry| entry.id)
+                                })
+                            {
+                                echoed.update(|echoed| {
+                                    echoed.insert(entry);
+                                });
+                            }
+                            list.update(|list| list.append(vec![chat_message]));
                             if should_scroll {
                                 request_animation_frame(move || {
                                     messages_area.set_scroll_top(messages_area.scroll_height());
@@ -223,50 +686,47 @@ pub fn Messages(
                     {
                         editing_message_id.set(None);
                         context_menu_state.set(None);
-                        messages.update(|msgs| {
-                            if let Some(pos) = msgs.iter().position(|m| m.id == updated_message.id)
-                            {
-                                msgs[pos] = updated_message;
-                                *msgs = msgs.clone();
-                            }
+                        list.update(|list| {
+                            list.modify(updated_message.id, |msg| *msg = updated_message)
                         });
                     }
                 }
                 WsMessageType::MessageDeleted => {
                     if let Ok(message_id) = serde_json::from_value::<Uuid>(ws_message.data) {
-                        messages.update(|msgs| msgs.retain(|m| m.id != message_id));
+                        list.update(|list| list.remove(message_id));
                     }
                 }
                 WsMessageType::MessagePinned => {
                     if let Ok(pinned_data) =
                         serde_json::from_value::<PinnedMessageData>(ws_message.data)
                     {
-                        messages.update(|msgs| {
-                            if let Some(msg) =
-                                msgs.iter_mut().find(|m| m.id == pinned_data.message_id)
-                            {
+                        list.update(|list| {
+                            list.modify(pinned_data.message_id, |msg| {
                                 msg.is_pinned = pinned_data.is_pinned;
                                 msg.updated_at = pinned_data.updated_at;
-                            }
+                            })
                         });
+                        pinned_messages.refetch();
                         if show_pinned.get_untracked() {
-                            pinned_messages.refetch();
+                            load_pinned_page(1);
                         }
                     }
                 }
                 WsMessageType::ChatUpdated => {
                     if let Ok(chat) = serde_json::from_value::<Chat>(ws_message.data) {
-                        if let Some(messages_area) = messages_area_ref.get() {
-                            if let Some(chat_message) = chat.last_message.clone() {
-                                let should_scroll = messages_area.scroll_top()
-                                    + messages_area.client_height()
-                                    >= messages_area.scroll_height() - 200;
-                                messages.update(|msgs| msgs.push(chat_message));
-                                if should_scroll {
-                                    request_animation_frame(move || {
-                                        messages_area.set_scroll_top(messages_area.scroll_height());
-                                    });
-                                }
+                        // Последнее сообщение обычно уже пришло через ReceiveMessage
+                        if let Some(messages_area) = messages_area_ref.get()
+                            && let Some(chat_message) = chat.last_message.clone()
+                            && !list.with_untracked(|list| list.contains(chat_message.id))
+                        {
+                            let should_scroll = messages_area.scroll_top()
+                                + messages_area.client_height()
+                                >= messages_area.scroll_height() - 200;
+                            list.update(|list| list.append(vec![chat_message]));
+                            if should_scroll {
+                                request_animation_frame(move || {
+                                    messages_area.set_scroll_top(messages_area.scroll_height());
+                                });
                             }
                         }
                         set_chat.set(Some(chat));
@@ -276,14 +736,59 @@ pub fn Messages(
                     refetch_chats.run(());
                     set_chat.set(None);
                 }
-                _ => {}
+                // Правки, удаления и закрепления, пропущенные за время разрыва
+                WsMessageType::Resync => {
+                    let loaded = list.with_untracked(|list| list.messages().count()) as u32;
+                    let limit = loaded.clamp(PAGE_SIZE, RESYNC_LIMIT);
+                    let abort = resync_request.next();
+                    spawn_local(async move {
+                        if let Ok(mut recent) =
+                            get_chat_messages(chat_id, None, Some(1), Some(limit), abort).await
+                        {
+                            let complete = recent.len() < limit as usize;
+                            recent.reverse();
+                            list.try_update(|list| list.sync(recent, complete));
+                        }
+                    });
+                    pinned_messages.refetch();
+                    if show_pinned.get_untracked() {
+                        load_pinned_page(1);
+                    }
+                    cache::invalidate(&chat_members_path(chat_id));
+                    chat_members.refetch();
+                }
+                // Исключение текущего пользователя обрабатывает список чатов
+                WsMessageType::MemberUpdated | WsMessageType::MemberDeleted => {
+                    if let Some(change) = MemberChange::receive(&ws_message) {
+                        chat_members.update(|members| {
+                            if let Some(Ok(members)) = members {
+                                change.apply(members);
+                            }
+                        });
+                    }
+                }
             }
         }
     });
 
+    // Черновик сохраняется, когда пользователь перестаёт печатать, и очищается
+    // вместе с полем при отправке. При закрытии чата недописанное не теряется
+    Effect::new(move |_| {
+        messsage_input.with(|text| drafts::save(chat_id, text));
+    });
+    on_cleanup(drafts::flush);
+
     Effect::new(move |_| {
         if show_pinned.get() {
-            pinned_messages.refetch();
+            load_pinned_page(1);
+        }
+    });
+
+    Effect::new(move |_| {
+        if show_search.get()
+            && let Some(input) = search_input_ref.get()
+        {
+            let _ = input.focus();
         }
     });
 
@@ -295,78 +800,374 @@ pub fn Messages(
         }
     });
 
+    let load_older_messages = move || async move {
+        if is_loading_more.get_untracked() || !has_more_messages.get_untracked() {
+            return;
+        }
+
+        is_loading_more.set(true);
+        current_page.update(|p| *p += 1);
+
+        // Видимые сообщения остаются на месте благодаря якорю прокрутки списка
+        if let Ok(mut new_messages) = get_chat_messages(
+            chat_id,
+            None,
+            Some(current_page.get_untracked()),
+            Some(PAGE_SIZE),
+            older_messages_request.next(),
+        )
+        .await
+        {
+            if new_messages.is_empty() || new_messages.len() < PAGE_SIZE as usize {
+                has_more_messages.set(false);
+            }
+
+            new_messages.reverse();
+            list.update(|list| list.prepend(new_messages));
+
+            request_animation_frame(move || {
+                is_loading_more.set(false);
+            });
+        } else {
+            is_loading_more.set(false);
+        }
+    };
+
     let _ = use_infinite_scroll_with_options(
         messages_area_ref,
-        move |_| async move {
-            if is_loading_more.get_untracked() || !has_more_messages.get_untracked() {
+        move |_| load_older_messages(),
+        UseInfiniteScrollOptions::default().direction(leptos_use::core::Direction::Top),
+    );
+
+    let first_unread = Memo::new(move |_| {
+        let (after, until) = unread_range.get()?;
+        list.with(|list| {
+            list.messages()
+                .find(|m| m.created_at <= until && unread::is_unread(m, after))
+                .map(|m| m.id)
+        })
+    });
+
+    Effect::new(move |_| {
+        let first_unread = first_unread.get();
+        list.update(|list| list.set_unread_divider(first_unread));
+    });
+
+    Effect::new(move |_| {
+        let pending =
+            pending_messages.with(|pending| pending.iter().map(pending_message).collect());
+        list.update(|list| list.set_pending(pending));
+    });
+
+    // Отрисовываются только элементы около видимой области, остальные заменяет
+    // отступ высотой по измеренным или, пока элемент не показывался, оценённым размерам
+    let visible_items = Memo::new(move |_| {
+        let (scroll_top, viewport) = scroll_position.get();
+        list.with(|list| {
+            let range = list.layout.visible_range(scroll_top, viewport);
+            let top_padding = list.layout.offset(range.start);
+            let bottom_padding = list.layout.total_height() - list.layout.offset(range.end);
+            (list.items[range].to_vec(), top_padding, bottom_padding)
+        })
+    });
+
+    // Где начинается список внутри прокручиваемой области: над ним отступ и спиннер
+    let list_offset = move |area: &HtmlDivElement| -> Option<f64> {
+        let list = message_list_ref.try_get_untracked().flatten()?;
+        let area_top = area.get_bounding_client_rect().top();
+        Some(list.get_bounding_client_rect().top() - area_top + area.scroll_top() as f64)
+    };
+
+    let measure_items = move |area: &HtmlDivElement| {
+        let Ok(elements) = area.query_selector_all("[data-key]") else {
+            return;
+        };
+        let changed = list.with_untracked(|list| {
+            (0..elements.length())
+                .filter_map(|i| elements.item(i)?.dyn_into::<Element>().ok())
+                .filter_map(|element| {
+                    let key = element.get_attribute("data-key")?;
+                    let height = element.get_bounding_client_rect().height();
+                    let known = list.heights.get(&key).copied();
+                    known
+                        .is_none_or(|known| (known - height).abs() > 0.5)
+                        .then_some((key, height))
+                })
+                .collect::<Vec<_>>()
+        });
+        if !changed.is_empty() {
+            list.update(|list| list.set_heights(changed));
+        }
+    };
+
+    // Запоминает положение прокрутки: какие элементы видны и что держать на месте
+    let sync_scroll = move || {
+        // Вызывается и из кадров анимации, которые могут пережить закрытие чата
+        let Some(area) = messages_area_ref.try_get_untracked().flatten() else {
+            return;
+        };
+        let Some(offset) = list_offset(&area) else {
+            return;
+        };
+        measure_items(&area);
+        let scroll_top = area.scroll_top() as f64 - offset;
+        let viewport = area.client_height() as f64;
+        if let Some(anchor) =
+            list.with_untracked(|list| list.layout.anchor_at(scroll_top, viewport))
+        {
+            scroll_anchor.set_value(anchor);
+        }
+        scroll_position.set((scroll_top, viewport));
+    };
+
+    // Догрузка старых страниц и уточнение высот сдвигают элементы: возвращаем якорь на место
+    Effect::new(move |_| {
+        list.track();
+        is_loading_more.track();
+        request_animation_frame(move || {
+            let Some(area) = messages_area_ref.try_get_untracked().flatten() else {
                 return;
+            };
+            let target = match scroll_anchor.try_get_value() {
+                Some(ScrollAnchor::Bottom) => Some(area.scroll_height() as f64),
+                Some(ScrollAnchor::Item { key, delta }) => list_offset(&area).and_then(|offset| {
+                    list.try_with_untracked(|list| {
+                        list.layout
+                            .index_of(&key)
+                            .map(|index| list.layout.offset(index) + delta + offset)
+                    })
+                    .flatten()
+                }),
+                None => None,
+            };
+            if let Some(target) = target
+                && (target - area.scroll_top() as f64).abs() >= 1.0
+            {
+                area.set_scroll_top(target.round() as i32);
             }
+            sync_scroll();
+        });
+    });
 
-            is_loading_more.set(true);
-            current_page.update(|p| *p += 1);
+    // Элемент может быть за пределами отрисованного окна: сначала прокручиваем
+    // к его расчётному месту, чтобы он появился в DOM
+    let scroll_to_item = move |key: &str| {
+        let Some(area) = messages_area_ref.get_untracked() else {
+            return;
+        };
+        let top = list.with_untracked(|list| {
+            list.layout
+                .index_of(key)
+                .map(|index| list.layout.offset(index))
+        });
+        if let (Some(top), Some(offset)) = (top, list_offset(&area)) {
+            area.set_scroll_top((top + offset).round() as i32);
+            sync_scroll();
+        }
+    };
 
-            if let Some(messages_area) = messages_area_ref.get_untracked() {
-                let old_scroll_height = messages_area.scroll_height();
+    let find_item = move |key: &str| -> Option<Element> {
+        messages_area_ref
+            .get_untracked()?
+            .query_selector(&format!("[data-key=\"{}\"]", key))
+            .ok()
+            .flatten()
+    };
 
-                if let Ok(mut new_messages) = get_chat_messages(
-                    chat_id,
-                    None,
-                    Some(current_page.get_untracked()),
-                    Some(PAGE_SIZE),
-                )
-                .await
-                {
-                    if new_messages.is_empty() || new_messages.len() < PAGE_SIZE as usize {
-                        has_more_messages.set(false);
+    // Догружает старые страницы, пока `found` не найдёт нужное или история не закончится
+    let load_older_until = move |found: Box<dyn Fn() -> Option<bool>>| async move {
+        while found() == Some(false) && has_more_messages.try_get_untracked() == Some(true) {
+            if is_loading_more.get_untracked() {
+                TimeoutFuture::new(50).await;
+                continue;
+            }
+            let loaded = list.with_untracked(|list| list.loaded);
+            load_older_messages().await;
+            // Страница не загрузилась: не повторяем запрос бесконечно
+            if list.try_with_untracked(|list| list.loaded) == Some(loaded) {
+                break;
+            }
+        }
+        while is_loading_more.try_get_untracked() == Some(true) {
+            TimeoutFuture::new(50).await;
+        }
+    };
+
+    let jump_to_unread = move || {
+        show_jump_to_unread.set(false);
+        spawn_local(async move {
+            // Первое непрочитанное может быть старше загруженных страниц
+            load_older_until(Box::new(move || {
+                first_unread.try_get_untracked().map(|id| id.is_some())
+            }))
+            .await;
+            request_animation_frame(move || {
+                let key = ListItem::UnreadDivider.key();
+                scroll_to_item(&key);
+                request_animation_frame(move || {
+                    if let Some(divider) = find_item(&key) {
+                        divider.scroll_into_view();
                     }
+                });
+            });
+        });
+    };
 
-                    new_messages.reverse();
-                    messages.update(|msgs| {
-                        let mut new_msgs = new_messages;
-                        new_msgs.extend(msgs.clone());
-                        *msgs = new_msgs;
-                    });
+    let scroll_to_message = move |message_id: Uuid| {
+        spawn_local(async move {
+            load_older_until(Box::new(move || {
+                list.try_with_untracked(|list| list.contains(message_id))
+            }))
+            .await;
+            request_animation_frame(move || {
+                let key = message_id.to_string();
+                scroll_to_item(&key);
+                request_animation_frame(move || {
+                    if let Some(element) = find_item(&key) {
+                        element.scroll_into_view();
+                        highlighted.set(Some(message_id));
+                        set_timeout(
+                            move || {
+                                highlighted.try_update(|id| {
+                                    if *id == Some(message_id) {
+                                        *id = None;
+                                    }
+                                });
+                            },
+                            Duration::from_secs(2),
+                        );
+                    }
+                });
+            });
+        });
+    };
 
-                    request_animation_frame(move || {
-                        let new_scroll_height = messages_area.scroll_height();
-                        messages_area.set_scroll_top(new_scroll_height - old_scroll_height);
-                        is_loading_more.set(false);
-                    });
-                } else {
-                    is_loading_more.set(false);
-                }
-            } else {
-                is_loading_more.set(false);
+    let search_text = Memo::new(move |_| {
+        if show_search.get() {
+            search_query.with(|query| query.trim().to_string())
+        } else {
+            String::new()
+        }
+    });
+
+    // Совпадения среди загруженных сообщений, от новых к старым
+    let search_results = Memo::new(move |_| {
+        let query = search_text.get();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        list.with(|list| {
+            list.messages()
+                .rev()
+                .filter(|m| {
+                    m.system_message_type == SystemMessageType::None
+                        && contains_match(reply::body(&m.message), &query)
+                })
+                .map(|m| m.id)
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let search_position = Memo::new(move |_| {
+        let current = search_current.get()?;
+        search_results.with(|results| results.iter().position(|id| *id == current))
+    });
+
+    let search_newer = move || {
+        if let Some(position) = search_position.get_untracked()
+            && position > 0
+        {
+            let id = search_results.with_untracked(|results| results[position - 1]);
+            search_current.set(Some(id));
+            scroll_to_message(id);
+        }
+    };
+
+    // Следующее совпадение в прошлом; когда загруженные кончились, листаем историю назад
+    let search_older = move || {
+        let next = search_position
+            .get_untracked()
+            .map_or(0, |position| position + 1);
+        if let Some(id) = search_results.with_untracked(|results| results.get(next).copied()) {
+            search_current.set(Some(id));
+            scroll_to_message(id);
+            return;
+        }
+        if is_searching_older.get_untracked() || !has_more_messages.get_untracked() {
+            return;
+        }
+        is_searching_older.set(true);
+        spawn_local(async move {
+            load_older_until(Box::new(move || {
+                search_results.try_with_untracked(|results| results.len() > next)
+            }))
+            .await;
+            // Значение возвращается, только если чат уже закрыт
+            if is_searching_older.try_set(false).is_some() {
+                return;
             }
-        },
-        UseInfiniteScrollOptions::default().direction(leptos_use::core::Direction::Top),
-    );
+            if let Some(id) = search_results.with_untracked(|results| results.get(next).copied()) {
+                search_current.set(Some(id));
+                scroll_to_message(id);
+            }
+        });
+    };
 
-    let messages_with_dates = Memo::new(move |_| {
-        let mut result: Vec<ListItem> = Vec::new();
-        let mut last_date: Option<NaiveDate> = None;
+    let close_search = move || {
+        show_search.set(false);
+        search_query.set(String::new());
+        search_current.set(None);
+    };
 
-        for msg in messages.get() {
-            let msg_date = msg.created_at.date();
-            if last_date.is_none() || last_date != Some(msg_date) {
-                result.push(ListItem::DateSeparator(msg_date));
-                last_date = Some(msg_date);
+    // Новый запрос начинается с самого свежего совпадения
+    Effect::new(move |prev_query: Option<String>| {
+        let query = search_text.get();
+        if prev_query.as_ref() != Some(&query) {
+            let newest = search_results.with_untracked(|results| results.first().copied());
+            search_current.set(newest);
+            if let Some(id) = newest {
+                scroll_to_message(id);
             }
-            result.push(ListItem::Message(msg));
         }
-        result
+        query
     });
 
     let on_submit = move || {
-        let msg = messsage_input.get_untracked();
-        if !msg.is_empty() {
-            let request = CreateChatMessageRequest {
+        let text = messsage_input.get_untracked();
+        let msg = reply::compose(
+            replying_to.get_untracked().map(|original| original.id),
+            &text,
+        );
+        if can_send(&text) && can_send(&msg) {
+            outbox::enqueue(CreateChatMessageRequest {
                 chat_id: Some(chat_id),
                 message: msg,
                 receiver_id: None,
-            };
+            });
             messsage_input.set(String::new());
-            send_message.dispatch(request);
+            replying_to.set(None);
+            if let Some(messages_area) = messages_area_ref.get_untracked() {
+                request_animation_frame(move || {
+                    messages_area.set_scroll_top(messages_area.scroll_height());
+                });
+            }
+        }
+    };
+
+    // Ссылка на исходное сообщение не редактируется и сохраняется как была
+    let edit_reply_to = move |message_id: Uuid| {
+        list.with_untracked(|list| {
+            list.message(message_id)
+                .and_then(|m| reply::split(&m.message).0)
+        })
+    };
+
+    let save_edit = move |message_id: Uuid| {
+        let text = edit_input.get_untracked();
+        let reply_to = edit_reply_to(message_id);
+        let new_message = reply::compose(reply_to, &text);
+        if can_send(&text) && can_send(&new_message) {
+            edit_message_action.dispatch((message_id, new_message));
         }
     };
 
@@ -377,6 +1178,11 @@ pub fn Messages(
             <div class=style::chat_header>
                 <img class=style::avatar src=chat_image onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
                 <span class=style::chat_name>{chat_name}</span>
+                {move || match realtime::status() {
+                    ConnectionStatus::Online => None,
+                    ConnectionStatus::Connecting => Some(view! { <span class=style::connection_status>"Подключение…"</span> }),
+                    ConnectionStatus::Offline => Some(view! { <span class=style::connection_status>"Нет соединения"</span> }),
+                }}
                 <Show when=move || chat.chat_type.clone() == ChatType::Group && is_member.get()>
                     <Show when=move || matches!(current_user_role.get(), Some(ChatMemberRole::Owner | ChatMemberRole::Administrator))>
                         <button class=style::header_button on:click=move |_| set_show_chat_settings_window.set(true)>
@@ -387,11 +1193,117 @@ pub fn Messages(
                         <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M12 10C14.2091 10 16 8.20914 16 6 16 3.79086 14.2091 2 12 2 9.79086 2 8 3.79086 8 6 8 8.20914 9.79086 10 12 10ZM5.5 13C6.88071 13 8 11.8807 8 10.5 8 9.11929 6.88071 8 5.5 8 4.11929 8 3 9.11929 3 10.5 3 11.8807 4.11929 13 5.5 13ZM21 10.5C21 11.8807 19.8807 13 18.5 13 17.1193 13 16 11.8807 16 10.5 16 9.11929 17.1193 8 18.5 8 19.8807 8 21 9.11929 21 10.5ZM12 11C14.7614 11 17 13.2386 17 16V22H7V16C7 13.2386 9.23858 11 12 11ZM5 15.9999C5 15.307 5.10067 14.6376 5.28818 14.0056L5.11864 14.0204C3.36503 14.2104 2 15.6958 2 17.4999V21.9999H5V15.9999ZM22 21.9999V17.4999C22 15.6378 20.5459 14.1153 18.7118 14.0056 18.8993 14.6376 19 15.307 19 15.9999V21.9999H22Z"></path></svg>
                     </button>
                 </Show>
+                <button class=style::header_button on:click=move |_| {
+                    if show_search.get_untracked() {
+                        close_search();
+                    } else {
+                        show_search.set(true);
+                    }
+                }>
+                    <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M18.031 16.6168L22.3137 20.8995L20.8995 22.3137L16.6168 18.031C15.0769 19.263 13.124 20 11 20C6.032 20 2 15.968 2 11C2 6.032 6.032 2 11 2C15.968 2 20 6.032 20 11C20 13.124 19.263 15.0769 18.031 16.6168ZM16.0247 15.8748C17.2475 14.6146 18 12.8956 18 11C18 7.1325 14.8675 4 11 4C7.1325 4 4 7.1325 4 11C4 14.8675 7.1325 18 11 18C12.8956 18 14.6146 17.2475 15.8748 16.0247L16.0247 15.8748Z"></path></svg>
+                </button>
                 <button class=style::header_button on:click=move |_| show_pinned.update(|v| *v = !*v)>
                     <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M22.3126 10.1753L20.8984 11.5895L20.1913 10.8824L15.9486 15.125L15.2415 18.6606L13.8273 20.0748L9.58466 15.8321L4.63492 20.7819L3.2207 19.3677L8.17045 14.4179L3.92781 10.1753L5.34202 8.76107L8.87756 8.05396L13.1202 3.81132L12.4131 3.10422L13.8273 1.69L22.3126 10.1753Z"></path></svg>
                 </button>
             </div>
 
+            <Show when=move || show_search.get()>
+                <div class=style::search_bar>
+                    <input
+                        type="text"
+                        placeholder="Поиск по сообщениям"
+                        node_ref=search_input_ref
+                        bind:value=search_query
+                        on:keydown=move |ev| {
+                            if ev.key() == "Enter" {
+                                ev.prevent_default();
+                                if ev.shift_key() {
+                                    search_newer();
+                                } else {
+                                    search_older();
+                                }
+                            } else if ev.key() == "Escape" {
+                                close_search();
+                            }
+                        }
+                    />
+                    <span class=style::search_status>
+                        {move || {
+                            let total = search_results.with(Vec::len);
+                            if search_text.with(String::is_empty) {
+                                String::new()
+                            } else if is_searching_older.get() {
+                                "Поиск в истории…".to_string()
+                            } else if total == 0 && has_more_messages.get() {
+                                "Нет в загруженных".to_string()
+                            } else if total == 0 {
+                                "Ничего не найдено".to_string()
+                            } else {
+                                let more = if has_more_messages.get() { "+" } else { "" };
+                                let position = search_position.get().map_or(0, |p| p + 1);
+                                format!("{} из {}{}", position, total, more)
+                            }
+                        }}
+                    </span>
+                    <button
+                        class=style::bar_button
+                        title="Раньше"
+                        disabled=move || {
+                            is_searching_older.get()
+                                || search_text.with(String::is_empty)
+                                || (!has_more_messages.get()
+                                    && search_position.get().map_or(0, |p| p + 1) >= search_results.with(Vec::len))
+                        }
+                        on:click=move |_| search_older()
+                    >
+                        <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M13 7.82843V20H11V7.82843L5.63604 13.1924L4.22183 11.7782L12 4L19.7782 11.7782L18.364 13.1924L13 7.82843Z"></path></svg>
+                    </button>
+                    <button
+                        class=style::bar_button
+                        title="Позже"
+                        disabled=move || search_position.get().is_none_or(|p| p == 0)
+                        on:click=move |_| search_newer()
+                    >
+                        <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M13 16.1716L18.364 10.8076L19.7782 12.2218L12 20L4.22183 12.2218L5.63604 10.8076L11 16.1716V4H13V16.1716Z"></path></svg>
+                    </button>
+                    <button class=style::bar_button title="Закрыть" on:click=move |_| close_search()>
+                        <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M11.9997 10.5865L16.9495 5.63672L18.3637 7.05093L13.4139 12.0007L18.3637 16.9504L16.9495 18.3646L11.9997 13.4149L7.04996 18.3646L5.63574 16.9504L10.5855 12.0007L5.63574 7.05093L7.04996 5.63672L11.9997 10.5865Z"></path></svg>
+                    </button>
+                </div>
+            </Show>
+
+            {move || current_pin.get().map(|(index, total, msg)| {
+                let message_id = msg.id;
+                view! {
+                    <div class=style::pinned_banner>
+                        <div
+                            class=style::pinned_banner_text
+                            on:click=move |_| {
+                                scroll_to_message(message_id);
+                                // Следующий клик ведёт к более раннему закреплённому
+                                pinned_index.set(index + 1);
+                            }
+                        >
+                            <strong>
+                                {if total > 1 {
+                                    format!("Закреплённое сообщение {} из {}", index + 1, total)
+                                } else {
+                                    "Закреплённое сообщение".to_string()
+                                }}
+                            </strong>
+                            <span>{reply::snippet(&msg.message)}</span>
+                        </div>
+                        <button
+                            class=style::bar_button
+                            title="Все закреплённые"
+                            on:click=move |_| show_pinned.update(|v| *v = !*v)
+                        >
+                            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M8 4H21V6H8V4ZM3 3.5H6V6.5H3V3.5ZM3 10.5H6V13.5H3V10.5ZM3 17.5H6V20.5H3V17.5ZM8 11H21V13H8V11ZM8 18H21V20H8V18Z"></path></svg>
+                        </button>
+                    </div>
+                }
+            })}
+
             <div
                 class=move || {
                     let mut classes = vec![style::pinned_messages_bar.to_string()];
@@ -401,160 +1313,260 @@ pub fn Messages(
                     classes.join(" ")
                 }
             >
-                {move || pinned_messages.get().map(|msgs| view! {
-                    <For
-                        each=move || msgs.clone()
-                        key=|msg| msg.id
-                        children=|msg| {
-                            view! {
-                                <div class=style::pinned_message_item>
-                                    <strong>{msg.sender_user_name.clone()}</strong>
-                                    <p>{msg.message.clone()}</p>
-                                    <Show when=move || msg.pinned_at.is_some()>
-                                        <span class=style::pinned_at_time>{msg.pinned_at.unwrap().format("%d.%m.%y %H:%M").to_string()}</span>
-                                    </Show>
-                                </div>
-                            }
+                <For
+                    each=move || pinned_list.get()
+                    key=|msg| msg.id
+                    children=move |msg| {
+                        let message_id = msg.id;
+                        view! {
+                            <div
+                                class=style::pinned_message_item
+                                on:click=move |_| {
+                                    show_pinned.set(false);
+                                    scroll_to_message(message_id);
+                                }
+                            >
+                                <strong>{msg.sender_user_name.clone()}</strong>
+                                <p>{reply::body(&msg.message).to_string()}</p>
+                                <Show when=move || msg.pinned_at.is_some()>
+                                    <span class=style::pinned_at_time>{msg.pinned_at.unwrap().format("%d.%m.%y %H:%M").to_string()}</span>
+                                </Show>
+                            </div>
                         }
-                    />
-                })}
+                    }
+                />
+                <Show when=move || !is_loading_pinned.get() && pinned_list.with(Vec::is_empty)>
+                    <p class=style::pinned_empty>"Закреплённых сообщений нет"</p>
+                </Show>
+                <Show when=move || is_loading_pinned.get()>
+                    <div class=style::spinner_container><Spinner/></div>
+                </Show>
+                <Show when=move || has_more_pinned.get() && !is_loading_pinned.get()>
+                    <button
+                        class=style::pinned_more
+                        on:click=move |_| load_pinned_page(pinned_page.get_untracked() + 1)
+                    >
+                        "Показать ещё"
+                    </button>
+                </Show>
             </div>
 
-            <div class=style::messages_area node_ref=messages_area_ref>
+            <div class=style::messages_area node_ref=messages_area_ref on:scroll=move |_| sync_scroll()>
                 <Show when=move || is_loading_more.get()>
                     <div class=style::spinner_container><Spinner/></div>
                 </Show>
                 <Suspense fallback=|| view! { <div class=style::spinner_container><Spinner/></div> }>
-                     <For
-                        each=move || messages_with_dates.get()
-                        key=|item| match item {
-                            ListItem::Message(msg) => (msg.id.to_string(), msg.updated_at.unwrap_or_default().to_string().clone()),
-                            ListItem::DateSeparator(date) => (date.to_string(), date.to_string()),
-                        }
-                        children=move |item| {
-                            match item {
-                                ListItem::Message(msg) => {
-                                    let message_class = if msg.is_my_message {
-                                        style::my_message.to_string()
-                                    } else {
-                                        style::other_message.to_string()
-                                    };
-                                    let chat_type_cloned_2 = chat_type_cloned.clone();
-                                    let created_at = msg.created_at;
-                                    let now = Utc::now();
-                                    let time_str = if created_at.date() == now.date_naive() {
-                                        created_at.format("%H:%M").to_string()
-                                    } else {
-                                        created_at.format("%d.%m.%y %H:%M").to_string()
-                                    };
-
-                                    match msg.system_message_type {
-                                        SystemMessageType::None => view! {
-                                            <div class=message_class>
-                                                <Show when=move || !msg.is_my_message>
-                                                    <img class=style::message_avatar src=format!("{}/avatar/{}", API_BASE_URL, msg.sender_id.unwrap_or_default()) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
-                                                </Show>
-                                                <div class=style::message_body>
-                                                    <Show when=move || !msg.is_my_message && chat_type_cloned_2.clone() != ChatType::Personal>
-                                                        <div class=style::sender_name>{msg.sender_user_name.clone()}</div>
+                    <div class=style::message_list node_ref=message_list_ref>
+                        <div style=move || visible_items.with(|(_, top, _)| format!("height: {}px", top))></div>
+                        <For
+                            each=move || visible_items.with(|(items, _, _)| items.clone())
+                            key=|item| match item {
+                                ListItem::Message(msg, position) => (msg.id.to_string(), msg.updated_at.unwrap_or_default().to_string().clone(), *position),
+                                ListItem::DateSeparator(date) => (date.to_string(), date.to_string(), GroupPosition::default()),
+                                ListItem::UnreadDivider => ("unread".to_string(), String::new(), GroupPosition::default()),
+                            }
+                            children=move |item| {
+                                let key = item.key();
+                                let content = match item {
+                                    ListItem::Message(msg, position) => {
+                                        let message_class = if msg.is_my_message {
+                                            style::my_message.to_string()
+                                        } else {
+                                            style::other_message.to_string()
+                                        };
+                                        // Внутри группы сообщения стоят плотнее
+                                        let message_class = if position.ends_group {
+                                            message_class
+                                        } else {
+                                            format!("{} {}", message_class, style::grouped)
+                                        };
+                                        let chat_type_cloned_2 = chat_type_cloned.clone();
+                                        let created_at = msg.created_at;
+                                        let now = Utc::now();
+                                        let time_str = if created_at.date() == now.date_naive() {
+                                            created_at.format("%H:%M").to_string()
+                                        } else {
+                                            created_at.format("%d.%m.%y %H:%M").to_string()
+                                        };
+                                        let send_state = move || outbox::state(msg.id);
+                                        let (reply_to, body) = reply::split(&msg.message);
+                                        let (forwarded_from, body) = forward::split(body);
+                                        let forwarded_from = forwarded_from.map(str::to_string);
+                                        let is_forwarded = forwarded_from.is_some();
+                                        let body = body.to_string();
+                                        let message_id = msg.id;
+                                        let message_class = move || {
+                                            let mut classes = vec![message_class.clone()];
+                                            if highlighted.get() == Some(message_id) {
+                                                classes.push(style::highlighted.to_string());
+                                            }
+                                            if search_current.get() == Some(message_id) {
+                                                classes.push(style::search_current.to_string());
+                                            }
+                                            classes.join(" ")
+                                        };
+
+                                        match msg.system_message_type {
+                                            SystemMessageType::None => view! {
+                                                <div class=message_class>
+                                                    <Show when=move || !msg.is_my_message>
+                                                        <Show
+                                                            when=move || position.starts_group
+                                                            fallback=|| view! { <div class=style::avatar_placeholder></div> }
+                                                        >
+                                                            <img class=style::message_avatar src=avatar_url(msg.sender_id.unwrap_or_default()) onerror="this.onerror=null;this.src='/images/userdefault.webp';"/>
+                                                        </Show>
                                                     </Show>
+                                                    <div class=style::message_body>
+                                                        <Show when=move || position.starts_group && !msg.is_my_message && chat_type_cloned_2.clone() != ChatType::Personal>
+                                                            <div class=style::sender_name>{msg.sender_user_name.clone()}</div>
+                                                        </Show>
 
-                                                    <Show
-                                                        when=move || editing_message_id.get() != Some(msg.id)
-                                                        fallback=move || {
-                                                            let msg_id = msg.id;
-                                                            view! {
-                                                                <div class=style::edit_container>
-                                                                    <input
-                                                                        type="text"
-                                                                        class=style::edit_input
-                                                                        bind:value=edit_input
-                                                                        on:keyup=move |ev| {
-                                                                            if ev.key() == "Enter" {
-                                                                                edit_message_action.dispatch((msg_id, edit_input.get()));
-                                                                            } else if ev.key() == "Escape" {
-                                                                                editing_message_id.set(None);
-                                                                            }
-                                                                        }
-                                                                    />
-                                                                    <div class=style::edit_buttons>
-                                                                        <button on:click=move |_| {
-                                                                            edit_message_action.dispatch((msg_id, edit_input.get()));
-                                                                        }>{"Сохранить"}</button>
-                                                                        <button on:click=move |_| editing_message_id.set(None)>{"Отмена"}</button>
+                                                        <Show
+                                                            when=move || editing_message_id.get() != Some(msg.id)
+                                                            fallback=move || {
+                                                                let msg_id = msg.id;
+                                                                view! {
+                                                                    <div class=style::edit_container>
+                                                                        <Composer
+                                                                            value=edit_input
+                                                                            on_submit=Callback::new(move |()| save_edit(msg_id))
+                                                                            on_cancel=Callback::new(move |()| editing_message_id.set(None))
+                                                                            placeholder="Текст сообщения"
+                                                                            autofocus=true
+                                                                            reserved=Signal::derive(move || {
+                                                                                edit_input.with(|text| reply::overhead(edit_reply_to(msg_id), text))
+                                                                            })
+                                                                        />
+                                                                        <div class=style::edit_buttons>
+                                                                            <button on:click=move |_| save_edit(msg_id)>{"Сохранить"}</button>
+                                                                            <button on:click=move |_| editing_message_id.set(None)>{"Отмена"}</button>
+                                                                        </div>
                                                                     </div>
-                                                                </div>
-                                                            }
-                                                        }
-                                                    >
-                                                        <div class=style::message_content on:contextmenu=move |ev| {
-                                                            ev.prevent_default();
-                                                            if let Some(area) = messages_area_ref.get() {
-                                                                let area_rect = area.get_bounding_client_rect();
-                                                                let x_offset = -150;
-                                                                let y_offset = -60;
-                                                                let x = ev.client_x() - area_rect.left() as i32 + area.scroll_left() as i32 + x_offset;
-                                                                let y = ev.client_y() - area_rect.top() as i32 + area.scroll_top() as i32 + y_offset;
-
-                                                                context_menu_state.set(Some(ContextMenuState {
-                                                                    message_id: msg.id,
-                                                                    is_my_message: msg.is_my_message,
-                                                                    is_pinned: msg.is_pinned,
-                                                                    x,
-                                                                    y,
-                                                                }));
+                                                                }
                                                             }
-                                                        }>
-                                                            <p>{msg.message.clone()}</p>
-                                                        </div>
-                                                    </Show>
+                                                        >
+                                                            <div class=style::message_content on:contextmenu=move |ev| {
+                                                                ev.prevent_default();
+                                                                if let Some(area) = messages_area_ref.get() {
+                                                                    let area_rect = area.get_bounding_client_rect();
+                                                                    let x_offset = -150;
+                                                                    let y_offset = -60;
+                                                                    let x = ev.client_x() - area_rect.left() as i32 + area.scroll_left() + x_offset;
+                                                                    let y = ev.client_y() - area_rect.top() as i32 + area.scroll_top() + y_offset;
 
-                                                    <div class=style::time_and_status>
-                                                        <Show when=move || msg.is_pinned>
-                                                            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M22.3126 10.1753L20.8984 11.5895L20.1913 10.8824L15.9486 15.125L15.2415 18.6606L13.8273 20.0748L9.58466 15.8321L4.63492 20.7819L3.2207 19.3677L8.17045 14.4179L3.92781 10.1753L5.34202 8.76107L8.87756 8.05396L13.1202 3.81132L12.4131 3.10422L13.8273 1.69L22.3126 10.1753Z"></path></svg>
+                                                                    context_menu_state.set(Some(ContextMenuState {
+                                                                        message_id: msg.id,
+                                                                        is_my_message: msg.is_my_message,
+                                                                        is_pinned: msg.is_pinned,
+                                                                        is_forwarded,
+                                                                        is_pending: send_state().is_some(),
+                                                                        x,
+                                                                        y,
+                                                                    }));
+                                                                }
+                                                            }>
+                                                                {forwarded_from.clone().map(|from| view! {
+                                                                    <div class=style::forwarded_header>"Переслано от "{from}</div>
+                                                                })}
+                                                                {reply_to.map(|reply_to| view! {
+                                                                    <div class=style::reply_quote on:click=move |_| scroll_to_message(reply_to)>
+                                                                        {move || {
+                                                                            let original = list.with(|list| {
+                                                                                list.message(reply_to)
+                                                                                    .map(|m| (reply_author(m), reply::snippet(&m.message)))
+                                                                            });
+                                                                            match original {
+                                                                                Some((author, snippet)) => view! {
+                                                                                    <strong>{author}</strong>
+                                                                                    <span>{snippet}</span>
+                                                                                }.into_any(),
+                                                                                // Исходное ещё не загружено: найдётся по клику
+                                                                                None if has_more_messages.get() => view! {
+                                                                                    <span>"Исходное сообщение"</span>
+                                                                                }.into_any(),
+                                                                                None => view! {
+                                                                                    <span class=style::reply_missing>"Сообщение удалено"</span>
+                                                                                }.into_any(),
+                                                                            }
+                                                                        }}
+                                                                    </div>
+                                                                })}
+                                                                <RichText text=body.clone() highlight=search_text/>
+                                                            </div>
                                                         </Show>
-                                                        <Show when=move || msg.is_edited>
-                                                            <span class=style::edited_indicator>"(изм.)"</span>
+
+                                                        <Show when=move || matches!(send_state(), Some(OutboxState::Failed { .. }))>
+                                                            <div class=style::send_failed>
+                                                                <span>"Не отправлено"</span>
+                                                                <button on:click=move |_| outbox::retry(msg.id)>"Повторить"</button>
+                                                                <button on:click=move |_| outbox::discard(msg.id)>"Удалить"</button>
+                                                            </div>
                                                         </Show>
-                                                        <span>{time_str}</span>
-                                                        {if msg.is_my_message {
-                                                            view! {
-                                                                <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
-                                                                    <path d="M11.602 13.7599L13.014 15.1719L21.4795 6.7063L22.8938 8.12051L13.014 18.0003L6.65 11.6363L8.06421 10.2221L10.189 12.3469L11.6025 13.7594L11.602 13.7599ZM11.6037 10.9322L16.5563 5.97949L17.9666 7.38977L13.014 12.3424L11.6037 10.9322ZM8.77698 16.5873L7.36396 18.0003L1 11.6363L2.41421 10.2221L3.82723 11.6352L3.82604 11.6363L8.77698 16.5873Z"></path>
-                                                                </svg>
-                                                            }.into_any()
-                                                        } else {
-                                                            view! { <span/> }.into_any()
-                                                        }}
+
+                                                        <div class=style::time_and_status>
+                                                            <Show when=move || msg.is_pinned>
+                                                                <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M22.3126 10.1753L20.8984 11.5895L20.1913 10.8824L15.9486 15.125L15.2415 18.6606L13.8273 20.0748L9.58466 15.8321L4.63492 20.7819L3.2207 19.3677L8.17045 14.4179L3.92781 10.1753L5.34202 8.76107L8.87756 8.05396L13.1202 3.81132L12.4131 3.10422L13.8273 1.69L22.3126 10.1753Z"></path></svg>
+                                                            </Show>
+                                                            <Show when=move || msg.is_edited>
+                                                                <span class=style::edited_indicator>"(изм.)"</span>
+                                                            </Show>
+                                                            <span>{time_str}</span>
+                                                            {move || match (msg.is_my_message, send_state()) {
+                                                                (_, Some(OutboxState::Queued | OutboxState::Sending { .. })) => view! {
+                                                                    <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+                                                                        <path d="M12 22C6.47715 22 2 17.5228 2 12C2 6.47715 6.47715 2 12 2C17.5228 2 22 6.47715 22 12C22 17.5228 17.5228 22 12 22ZM12 20C16.4183 20 20 16.4183 20 12C20 7.58172 16.4183 4 12 4C7.58172 4 4 7.58172 4 12C4 16.4183 7.58172 20 12 20ZM13 12H17V14H11V7H13V12Z"></path>
+                                                                    </svg>
+                                                                }.into_any(),
+                                                                (_, Some(OutboxState::Failed { .. })) => view! {
+                                                                    <svg class=style::failed_icon xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+                                                                        <path d="M12 22C6.47715 22 2 17.5228 2 12C2 6.47715 6.47715 2 12 2C17.5228 2 22 6.47715 22 12C22 17.5228 17.5228 22 12 22ZM12 20C16.4183 20 20 16.4183 20 12C20 7.58172 16.4183 4 12 4C7.58172 4 4 7.58172 4 12C4 16.4183 7.58172 20 12 20ZM11 15H13V17H11V15ZM11 7H13V13H11V7Z"></path>
+                                                                    </svg>
+                                                                }.into_any(),
+                                                                (true, None) => view! {
+                                                                    <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+                                                                        <path d="M11.602 13.7599L13.014 15.1719L21.4795 6.7063L22.8938 8.12051L13.014 18.0003L6.65 11.6363L8.06421 10.2221L10.189 12.3469L11.6025 13.7594L11.602 13.7599ZM11.6037 10.9322L16.5563 5.97949L17.9666 7.38977L13.014 12.3424L11.6037 10.9322ZM8.77698 16.5873L7.36396 18.0003L1 11.6363L2.41421 10.2221L3.82723 11.6352L3.82604 11.6363L8.77698 16.5873Z"></path>
+                                                                    </svg>
+                                                                }.into_any(),
+                                                                (false, None) => view! { <span/> }.into_any(),
+                                                            }}
+                                                        </div>
                                                     </div>
                                                 </div>
-                                            </div>
-                                        }.into_any(),
-                                        _ => view! {
-                                            <div class=style::system_message>
-                                                <p>{msg.message.clone()}</p>
+                                            }.into_any(),
+                                            _ => view! {
+                                                <div class=style::system_message>
+                                                    <p>{msg.message.clone()}</p>
+                                                </div>
+                                            }.into_any()
+                                        }
+                                    }
+                                    ListItem::DateSeparator(date) => {
+                                        let today = Utc::now().date_naive();
+                                        let date_str = if date == today {
+                                            "Сегодня".to_string()
+                                        } else if date == today.pred_opt().unwrap_or(today) {
+                                            "Вчера".to_string()
+                                        } else {
+                                            date.format("%d %B %Y").to_string()
+                                        };
+                                        view! {
+                                            <div class=style::date_separator>
+                                                <span>{date_str}</span>
                                             </div>
                                         }.into_any()
                                     }
-                                }
-                                ListItem::DateSeparator(date) => {
-                                    let today = Utc::now().date_naive();
-                                    let date_str = if date == today {
-                                        "Сегодня".to_string()
-                                    } else if date == today.pred_opt().unwrap_or(today) {
-                                        "Вчера".to_string()
-                                    } else {
-                                        date.format("%d %B %Y").to_string()
-                                    };
-                                    view! {
-                                        <div class=style::date_separator>
-                                            <span>{date_str}</span>
+                                    ListItem::UnreadDivider => view! {
+                                        <div class=style::unread_divider>
+                                            <span>"Новые сообщения"</span>
                                         </div>
-                                    }.into_any()
-                                }
+                                    }.into_any(),
+                                };
+                                view! { <div class=style::list_item data-key=key>{content}</div> }
                             }
-                        }
-                    />
+                        />
+                        <div style=move || visible_items.with(|(_, _, bottom)| format!("height: {}px", bottom))></div>
+                    </div>
                 </Suspense>
                 <Show when=move || context_menu_state.get().is_some()>
                     {move || context_menu_state.get().map(|state| {
@@ -563,22 +1575,50 @@ pub fn Messages(
                                 class=style::context_menu
                                 style=format!("left: {}px; top: {}px;", state.x, state.y)
                             >
-                                <button on:click=move |_| {
-                                    pin_message_action.dispatch((state.message_id, !state.is_pinned));
-                                    context_menu_state.set(None);
-                                }>{if state.is_pinned {"Открепить"} else {"Закрепить"}}</button>
-                                <Show when=move || state.is_my_message>
+                                // С неотправленным сообщением серверу пока делать нечего
+                                <Show
+                                    when=move || !state.is_pending
+                                    fallback=move || view! {
+                                        <button on:click=move |_| {
+                                            outbox::discard(state.message_id);
+                                            context_menu_state.set(None);
+                                        }>{"Удалить"}</button>
+                                    }
+                                >
                                     <button on:click=move |_| {
-                                        if let Some(msg) = messages.get().iter().find(|m| m.id == state.message_id).cloned() {
-                                            edit_input.set(msg.message);
-                                            editing_message_id.set(Some(state.message_id));
-                                        }
+                                        pin_message_action.dispatch((state.message_id, !state.is_pinned));
                                         context_menu_state.set(None);
-                                    }>{"Редактировать"}</button>
+                                    }>{if state.is_pinned {"Открепить"} else {"Закрепить"}}</button>
+                                    <Show when=move || is_member.get()>
+                                        <button on:click=move |_| {
+                                            if let Some(msg) = list.with_untracked(|list| list.message(state.message_id).cloned()) {
+                                                replying_to.set(Some(msg));
+                                            }
+                                            context_menu_state.set(None);
+                                        }>{"Ответить"}</button>
+                                    </Show>
                                     <button on:click=move |_| {
-                                        delete_message_action.dispatch(state.message_id);
+                                        if let Some(text) = list.with_untracked(|list| list.message(state.message_id).map(forward_text)) {
+                                            forwarding.set(Some(text));
+                                        }
                                         context_menu_state.set(None);
-                                    }>{"Удалить"}</button>
+                                    }>{"Переслать"}</button>
+                                    <Show when=move || state.is_my_message>
+                                        // Пересланный текст принадлежит другому автору и не редактируется
+                                        <Show when=move || !state.is_forwarded>
+                                            <button on:click=move |_| {
+                                                if let Some(msg) = list.with_untracked(|list| list.message(state.message_id).cloned()) {
+                                                    edit_input.set(reply::body(&msg.message).to_string());
+                                                    editing_message_id.set(Some(state.message_id));
+                                                }
+                                                context_menu_state.set(None);
+                                            }>{"Редактировать"}</button>
+                                        </Show>
+                                        <button on:click=move |_| {
+                                            delete_message_action.dispatch(state.message_id);
+                                            context_menu_state.set(None);
+                                        }>{"Удалить"}</button>
+                                    </Show>
                                 </Show>
                             </div>
                         }
@@ -586,7 +1626,26 @@ pub fn Messages(
                 </Show>
             </div>
 
+            <Show when=move || unread_range.get().is_some() && show_jump_to_unread.get()>
+                <button class=style::jump_to_unread on:click=move |_| jump_to_unread()>
+                    "К непрочитанным"
+                </button>
+            </Show>
+
             <Show when=move || is_member.get()>
+                {move || replying_to.get().map(|original| view! {
+                    <div class=style::reply_preview>
+                        <div class=style::reply_preview_text>
+                            <strong>{reply_author(&original)}</strong>
+                            <span>{reply::snippet(&original.message)}</span>
+                        </div>
+                        <button class=style::reply_preview_close on:click=move |_| replying_to.set(None)>
+                            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M11.9997 10.5865L16.9495 5.63672L18.3637 7.05093L13.4139 12.0007L18.3637 16.9504L16.9495 18.3646L11.9997 13.4149L7.04996 18.3646L5.63574 16.9504L10.5855 12.0007L5.63574 7.05093L7.04996 5.63672L11.9997 10.5865Z"></path>
+                            </svg>
+                        </button>
+                    </div>
+                })}
                 <div class=style::message_input_area>
                     <button class=style::icon_button>
                         <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
@@ -594,15 +1653,15 @@ pub fn Messages(
                         </svg>
                     </button>
                     <form on:submit=|ev| ev.prevent_default()>
-                        <input
-                            type="text"
+                        <Composer
+                            value=messsage_input
+                            on_submit=Callback::new(move |()| on_submit())
+                            on_cancel=Callback::new(move |()| replying_to.set(None))
                             placeholder="Напишите сообщение..."
-                            bind:value=messsage_input
-                            on:keyup=move |ev| {
-                                if ev.key() == "Enter" {
-                                    on_submit();
-                                }
-                            }
+                            reserved=Signal::derive(move || {
+                                let reply_to = replying_to.with(|original| original.as_ref().map(|original| original.id));
+                                messsage_input.with(|text| reply::overhead(reply_to, text))
+                            })
                         />
                         <button
                             class=format!("{} {}", style::icon_button, style::send_button)
@@ -615,6 +1674,9 @@ pub fn Messages(
                     </form>
                 </div>
             </Show>
+            {move || forwarding.get().map(|message| view! {
+                <ForwardWindow message=message on_close=Callback::new(move |()| forwarding.set(None))/>
+            })}
             <Show when=move || show_chat_settings_window.get()>
                 <ChatSettingsWindow
                     chat=chat_cloned.clone()
@@ -632,3 +1694,259 @@ pub fn Messages(
         </div>
     }
 }
+
+/// Имя автора в цитате ответа.
+fn reply_author(msg: &ChatMessage) -> String {
+    if msg.is_my_message {
+        "Вы".to_string()
+    } else {
+        msg.sender_user_name.clone().unwrap_or_default()
+    }
+}
+
+/// Текст для пересылки: без ссылки на ответ и с исходным автором,
+/// даже если сообщение уже было переслано.
+fn forward_text(msg: &ChatMessage) -> String {
+    let (forwarded_from, body) = forward::split(reply::split(&msg.message).1);
+    let from = match (
+        forwarded_from,
+        &msg.sender_first_name,
+        &msg.sender_last_name,
+    ) {
+        (Some(from), _, _) => from.to_string(),
+        (None, Some(first_name), Some(last_name)) => format!("{} {}", first_name, last_name),
+        (None, _, _) => msg.sender_user_name.clone().unwrap_or_default(),
+    };
+    forward::encode(&from, body)
+}
+
+/// Сообщение из очереди отправки в виде обычного своего сообщения.
+fn pending_message(entry: &OutboxEntry) -> ChatMessage {
+    ChatMessage {
+        id: entry.id,
+        sender_id: get_current_user_id(),
+        sender_user_name: None,
+        sender_first_name: None,
+        sender_last_name: None,
+        sender_is_online: None,
+        system_message_type: SystemMessageType::None,
+        message: entry.request.message.clone(),
+        is_edited: false,
+        is_pinned: false,
+        created_at: entry.created_at,
+        updated_at: None,
+        pinned_at: None,
+        is_my_message: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender: u128, minute: u32) -> ChatMessage {
+        ChatMessage {
+            id: Uuid::new_v4(),
+            sender_id: Some(Uuid::from_u128(sender)),
+            sender_user_name: None,
+            sender_first_name: None,
+            sender_last_name: None,
+            sender_is_online: None,
+            system_message_type: SystemMessageType::None,
+            message: String::new(),
+            is_edited: false,
+            is_pinned: false,
+            created_at: NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(12, minute, 0)
+                .unwrap(),
+            updated_at: None,
+            pinned_at: None,
+            is_my_message: false,
+        }
+    }
+
+    fn grouped(items: Vec<ListItem>) -> Vec<Option<(bool, bool)>> {
+        let mut items = items;
+        group_messages(&mut items);
+        items
+            .iter()
+            .map(|item| match item {
+                ListItem::Message(_, position) => {
+                    Some((position.starts_group, position.ends_group))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn item(msg: ChatMessage) -> ListItem {
+        ListItem::Message(msg, GroupPosition::default())
+    }
+
+    #[test]
+    fn groups_consecutive_messages_of_one_sender() {
+        assert_eq!(
+            grouped(vec![
+                item(message(1, 0)),
+                item(message(1, 1)),
+                item(message(1, 2)),
+                item(message(2, 3)),
+            ]),
+            vec![
+                Some((true, false)),
+                Some((false, false)),
+                Some((false, true)),
+                Some((true, true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_pause_starts_new_group() {
+        assert_eq!(
+            grouped(vec![
+                item(message(1, 0)),
+                item(message(1, 5)),
+                item(message(1, 11))
+            ]),
+            vec![Some((true, false)), Some((false, true)), Some((true, true))]
+        );
+    }
+
+    #[test]
+    fn separators_break_groups() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        assert_eq!(
+            grouped(vec![
+                item(message(1, 0)),
+                ListItem::UnreadDivider,
+                item(message(1, 1)),
+                ListItem::DateSeparator(date),
+                item(message(1, 2)),
+            ]),
+            vec![
+                Some((true, true)),
+                None,
+                Some((true, true)),
+                None,
+                Some((true, true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn system_messages_break_groups() {
+        let mut system = message(1, 1);
+        system.system_message_type = SystemMessageType::ChatCreated;
+        assert_eq!(
+            grouped(vec![item(message(1, 0)), item(system), item(message(1, 2))]),
+            vec![Some((true, true)), Some((true, true)), Some((true, true))]
+        );
+    }
+
+    fn on_day(mut msg: ChatMessage, day: u32) -> ChatMessage {
+        msg.created_at = NaiveDate::from_ymd_opt(2024, 5, day)
+            .unwrap()
+            .and_time(msg.created_at.time());
+        msg
+    }
+
+    /// Список, построенный целиком заново, с которым сверяется изменённый по частям.
+    fn rebuilt(
+        messages: &[ChatMessage],
+        first_unread: Option<Uuid>,
+        pending: &[ChatMessage],
+    ) -> Vec<ListItem> {
+        let mut items = Vec::new();
+        let mut last_date = None;
+        for msg in messages {
+            items.extend(with_dates(vec![msg.clone()], last_date));
+            last_date = Some(msg.created_at.date());
+            if Some(msg.id) == first_unread {
+                let index = items.len() - 1;
+                items.insert(index, ListItem::UnreadDivider);
+            }
+        }
+        items.extend(with_dates(pending.to_vec(), last_date));
+        group_messages(&mut items);
+        items
+    }
+
+    fn assert_matches(list: &MessageList, expected: Vec<ListItem>) {
+        let layout = ListLayout::new(
+            expected
+                .iter()
+                .map(|item| (item.key(), item.estimated_height())),
+        );
+        assert_eq!(list.items, expected);
+        assert_eq!(list.layout, layout);
+    }
+
+    #[test]
+    fn prepending_and_appending_matches_full_rebuild() {
+        let all = vec![
+            on_day(message(1, 0), 1),
+            on_day(message(1, 0), 2),
+            on_day(message(1, 1), 2),
+            on_day(message(2, 2), 2),
+            on_day(message(2, 3), 2),
+            on_day(message(2, 0), 3),
+        ];
+        let mut list = MessageList::default();
+        list.reset(all[2..4].to_vec());
+        list.prepend(all[..2].to_vec());
+        list.append(all[4..].to_vec());
+        assert_matches(&list, rebuilt(&all, None, &[]));
+    }
+
+    #[test]
+    fn removing_last_message_of_day_drops_its_separator() {
+        let mut all = vec![
+            on_day(message(1, 0), 1),
+            on_day(message(1, 0), 2),
+            on_day(message(1, 1), 2),
+        ];
+        let mut list = MessageList::default();
+        list.reset(all.clone());
+        list.remove(all[1].id);
+        all.remove(1);
+        assert_matches(&list, rebuilt(&all, None, &[]));
+        list.remove(all[0].id);
+        all.remove(0);
+        assert_matches(&list, rebuilt(&all, None, &[]));
+    }
+
+    #[test]
+    fn unread_divider_and_pending_follow_changes() {
+        let all = vec![message(1, 0), message(1, 1), message(1, 2)];
+        let pending = vec![on_day(message(1, 3), 2)];
+        let mut list = MessageList::default();
+        list.reset(all[..2].to_vec());
+        list.set_pending(pending.clone());
+        list.set_unread_divider(Some(all[1].id));
+        list.append(all[2..].to_vec());
+        assert_matches(&list, rebuilt(&all, Some(all[1].id), &pending));
+
+        list.set_unread_divider(Some(all[2].id));
+        list.set_pending(Vec::new());
+        assert_matches(&list, rebuilt(&all, Some(all[2].id), &[]));
+    }
+
+    #[test]
+    fn sync_applies_changes_missed_while_offline() {
+        let mut all = vec![message(1, 0), message(1, 1), message(2, 2), message(2, 3)];
+        let mut list = MessageList::default();
+        list.reset(all.clone());
+
+        // Сервер вернул только последние: старше них удалять нечего
+        all.remove(2);
+        all[2].message = "правка".to_string();
+        all.push(message(1, 4));
+        list.sync(all[1..].to_vec(), false);
+        assert_matches(&list, rebuilt(&all, None, &[]));
+
+        list.sync(all[2..].to_vec(), true);
+        assert_matches(&list, rebuilt(&all[2..], None, &[]));
+    }
+}
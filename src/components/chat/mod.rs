@@ -1,4 +1,9 @@
+pub mod chat_members_panel;
+pub mod chat_settings_window;
+pub mod composer;
 pub mod create_chat_window;
+pub mod forward;
+pub mod forward_window;
 pub mod messages;
-pub mod chat_settings_window;
-pub mod chat_members_panel;
+pub mod reply;
+pub mod virtual_list;
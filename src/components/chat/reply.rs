@@ -0,0 +1,105 @@
+use super::forward;
+use uuid::Uuid;
+
+/// Контракт бэкенда передаёт у сообщения только текст, поэтому ссылка на исходное
+/// сообщение едет первой строкой текста: `[reply:<id>]`. Клиенты без поддержки
+/// ответов покажут эту строку как есть, остальной текст не меняется.
+const REPLY_PREFIX: &str = "[reply:";
+
+/// Невидимый U+2060, которым приложение экранирует текст, похожий на служебную строку.
+const ESCAPE: char = '\u{2060}';
+
+/// Длина превью исходного сообщения в цитате и над полем ввода.
+const SNIPPET_LENGTH: usize = 80;
+
+/// Текст для отправки: экранированный текст пользователя и, если это ответ,
+/// служебная строка перед ним. Все тексты из приложения проходят через эту
+/// функцию или через `forward::encode`.
+pub fn compose(reply_to: Option<Uuid>, text: &str) -> String {
+    let text = escape(text);
+    match reply_to {
+        Some(reply_to) => format!("{}{}]\n{}", REPLY_PREFIX, reply_to, text),
+        None => text,
+    }
+}
+
+/// Сколько символов `compose` добавит к тексту.
+pub fn overhead(reply_to: Option<Uuid>, text: &str) -> usize {
+    compose(reply_to, text).chars().count() - text.chars().count()
+}
+
+/// Набранный вручную `[reply:…]` или `[forwarded:…]` в начале текста иначе
+/// выглядел бы как ответ или пересылка, например «Переслано от» чужого имени.
+/// Такой текст предваряется `ESCAPE`, и служебная строка в нём не распознаётся.
+/// От клиента, который шлёт текст в API напрямую, это не защищает: контракт
+/// бэкенда не передаёт, кем создано сообщение.
+pub(super) fn escape(text: &str) -> String {
+    if text.starts_with(REPLY_PREFIX) || text.starts_with(forward::FORWARD_PREFIX) {
+        format!("{}{}", ESCAPE, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Исходное сообщение, на которое отвечают, и текст без служебной строки.
+pub fn split(message: &str) -> (Option<Uuid>, &str) {
+    let parsed = message.strip_prefix(REPLY_PREFIX).and_then(|rest| {
+        let (header, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        let id = header.strip_suffix(']')?.parse().ok()?;
+        Some((id, body))
+    });
+    match parsed {
+        Some((id, body)) => (Some(id), body),
+        None => (None, message),
+    }
+}
+
+/// Текст без служебных строк ответа и пересылки.
+pub fn body(message: &str) -> &str {
+    forward::split(split(message).1).1
+}
+
+/// Первая строка текста, укороченная для превью.
+pub fn snippet(message: &str) -> String {
+    let line = body(message).lines().next().unwrap_or_default();
+    if line.chars().count() > SNIPPET_LENGTH {
+        let short: String = line.chars().take(SNIPPET_LENGTH).collect();
+        format!("{}…", short.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_reference_survives_round_trip() {
+        let id = Uuid::from_u128(7);
+        let message = compose(Some(id), "Согласен\nи ещё строка");
+        assert_eq!(split(&message), (Some(id), "Согласен\nи ещё строка"));
+    }
+
+    #[test]
+    fn typed_markers_are_not_parsed() {
+        let typed = format!("[reply:{}]\nтекст", Uuid::from_u128(7));
+        assert_eq!(split(&compose(None, &typed)).0, None);
+
+        let message = compose(Some(Uuid::from_u128(7)), "[forwarded:Admin]\nтекст");
+        let (_, rest) = split(&message);
+        assert_eq!(forward::split(rest).0, None);
+        assert_eq!(
+            body(&message).trim_start_matches(ESCAPE),
+            "[forwarded:Admin]\nтекст"
+        );
+    }
+
+    #[test]
+    fn overhead_counts_reply_line_and_escape() {
+        let id = Uuid::from_u128(7);
+        assert_eq!(overhead(None, "текст"), 0);
+        assert_eq!(overhead(Some(id), "текст"), "[reply:]\n".len() + 36);
+        assert_eq!(overhead(None, "[reply:x]"), 1);
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::Range;
+
+/// Сколько пикселей сверху и снизу от видимой области держать отрисованными,
+/// чтобы при прокрутке не мелькали пустые места.
+const OVERSCAN: f64 = 600.0;
+
+/// Насколько можно не докрутить до низа, чтобы список всё ещё считался прижатым к нему.
+const BOTTOM_SLACK: f64 = 4.0;
+
+/// Что должно остаться на месте, когда высоты или состав списка меняются.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScrollAnchor {
+    /// Список прижат к последнему сообщению
+    Bottom,
+    /// Верх видимой области на `delta` ниже начала элемента `key`
+    Item { key: String, delta: f64 },
+}
+
+/// Положения элементов списка по известным или оценённым высотам.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListLayout {
+    keys: Vec<String>,
+    heights: Vec<f64>,
+    /// Начало каждого элемента и в конце общая высота: `offsets.len() == keys.len() + 1`
+    offsets: Vec<f64>,
+}
+
+impl Default for ListLayout {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl ListLayout {
+    pub fn new(items: impl IntoIterator<Item = (String, f64)>) -> Self {
+        let mut layout = Self {
+            keys: Vec::new(),
+            heights: Vec::new(),
+            offsets: vec![0.0],
+        };
+        layout.splice(0..0, items);
+        layout
+    }
+
+    /// Заменяет элементы `range` на `items`. Положения пересчитываются только
+    /// начиная с изменённого места, так что дописывание в конец почти бесплатно.
+    pub fn splice(&mut self, range: Range<usize>, items: impl IntoIterator<Item = (String, f64)>) {
+        let start = range.start;
+        let (keys, heights): (Vec<_>, Vec<_>) = items.into_iter().unzip();
+        self.keys.splice(range.clone(), keys);
+        self.heights.splice(range, heights);
+        self.update_offsets(start);
+    }
+
+    /// Обновляет высоты элементов по индексам.
+    pub fn set_heights(&mut self, heights: impl IntoIterator<Item = (usize, f64)>) {
+        let mut first = None;
+        for (index, height) in heights {
+            if let Some(known) = self.heights.get_mut(index) {
+                *known = height;
+                first = Some(first.map_or(index, |first: usize| first.min(index)));
+            }
+        }
+        if let Some(first) = first {
+            self.update_offsets(first);
+        }
+    }
+
+    fn update_offsets(&mut self, start: usize) {
+        self.offsets.truncate(start + 1);
+        let mut total = self.offsets[start];
+        for height in &self.heights[start..] {
+            total += height;
+            self.offsets.push(total);
+        }
+    }
+
+    pub fn total_height(&self) -> f64 {
+        self.offsets.last().copied().unwrap_or_default()
+    }
+
+    pub fn offset(&self, index: usize) -> f64 {
+        self.offsets[index.min(self.keys.len())]
+    }
+
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        self.keys.iter().position(|k| k == key)
+    }
+
+    /// Элементы, которые нужно отрисовать для области `[scroll_top, scroll_top + viewport)`.
+    pub fn visible_range(&self, scroll_top: f64, viewport: f64) -> Range<usize> {
+        let top = scroll_top - OVERSCAN;
+        let bottom = scroll_top + viewport + OVERSCAN;
+        let start = self.offsets[1..].partition_point(|&end| end <= top);
+        let end = self.offsets[..self.keys.len()].partition_point(|&begin| begin < bottom);
+        start..end.max(start)
+    }
+
+    /// Запоминает первый видимый элемент, либо что список прижат к низу.
+    pub fn anchor_at(&self, scroll_top: f64, viewport: f64) -> Option<ScrollAnchor> {
+        if scroll_top + viewport >= self.total_height() - BOTTOM_SLACK {
+            return Some(ScrollAnchor::Bottom);
+        }
+        let index = self.offsets[1..].partition_point(|&end| end <= scroll_top);
+        let key = self.keys.get(index)?.clone();
+        Some(ScrollAnchor::Item {
+            key,
+            delta: scroll_top - self.offsets[index],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` элементов высотой 100 с ключами-номерами.
+    fn layout(count: usize) -> ListLayout {
+        ListLayout::new((0..count).map(|i| (i.to_string(), 100.0)))
+    }
+
+    #[test]
+    fn visible_range_covers_viewport_with_overscan() {
+        // Видно 2000..2500, с запасом 1400..3100
+        assert_eq!(layout(50).visible_range(2000.0, 500.0), 14..31);
+        assert_eq!(layout(50).visible_range(0.0, 500.0), 0..11);
+        assert_eq!(layout(50).visible_range(4500.0, 500.0), 39..50);
+    }
+
+    #[test]
+    fn visible_range_of_empty_or_short_list() {
+        assert_eq!(ListLayout::default().visible_range(0.0, 500.0), 0..0);
+        assert_eq!(layout(3).visible_range(0.0, 500.0), 0..3);
+    }
+
+    #[test]
+    fn anchor_at_bottom_within_slack() {
+        assert_eq!(
+            layout(50).anchor_at(4500.0, 500.0),
+            Some(ScrollAnchor::Bottom)
+        );
+        assert_eq!(
+            layout(50).anchor_at(4497.0, 500.0),
+            Some(ScrollAnchor::Bottom)
+        );
+        assert_eq!(
+            ListLayout::default().anchor_at(0.0, 500.0),
+            Some(ScrollAnchor::Bottom)
+        );
+    }
+
+    #[test]
+    fn anchor_at_first_visible_item() {
+        assert_eq!(
+            layout(50).anchor_at(250.0, 500.0),
+            Some(ScrollAnchor::Item {
+                key: "2".to_string(),
+                delta: 50.0
+            })
+        );
+        // Граница элементов относится к следующему
+        assert_eq!(
+            layout(50).anchor_at(300.0, 500.0),
+            Some(ScrollAnchor::Item {
+                key: "3".to_string(),
+                delta: 0.0
+            })
+        );
+    }
+
+    #[test]
+    fn splice_and_set_heights_match_fresh_layout() {
+        let mut list = layout(3);
+        list.splice(0..0, [("a".to_string(), 40.0)]);
+        list.splice(4..4, [("b".to_string(), 60.0)]);
+        list.splice(2..3, []);
+        list.set_heights([(1, 70.0)]);
+        let expected = ListLayout::new([
+            ("a".to_string(), 40.0),
+            ("0".to_string(), 70.0),
+            ("2".to_string(), 100.0),
+            ("b".to_string(), 60.0),
+        ]);
+        assert_eq!(list, expected);
+        assert_eq!(list.total_height(), 270.0);
+        assert_eq!(list.offset(2), 110.0);
+    }
+}
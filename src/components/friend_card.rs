@@ -1,4 +1,4 @@
-use crate::{models::user::UserListItem, utils::API_BASE_URL};
+use crate::{models::user::UserListItem, utils::avatar_url};
 use leptos::prelude::*;
 use leptos_router::components::A;
 use stylance::import_style;
@@ -6,7 +6,7 @@ import_style!(style, "friend_card.module.scss");
 
 #[component]
 pub fn FriendCard(friend: UserListItem) -> impl IntoView {
-    let avatar_url = format!("{}/avatar/{}", API_BASE_URL, friend.user_id);
+    let avatar_url = avatar_url(friend.user_id);
     let is_online = move || friend.is_online;
 
     view! {
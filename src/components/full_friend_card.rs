@@ -1,13 +1,16 @@
 use crate::{
     api::{
-        chat_messages::{CreateChatMessageRequest, create_chat_message},
+        chat_messages::CreateChatMessageRequest,
+        error::ApiError,
         friends::{
             CreateFriendshipLabelRequest, UpdateFriendshipStatusRequest, create_friendship_label,
             delete_friendship_label, update_friendship_status,
         },
+        outbox::{self, OutboxState},
     },
+    components::chat::reply,
     models::friends::{Friendship, FriendshipStatus},
-    utils::API_BASE_URL,
+    utils::avatar_url,
 };
 use leptos::prelude::*;
 use leptos_router::components::A;
@@ -19,7 +22,7 @@ import_style!(style, "full_friend_card.module.scss");
 
 #[component]
 pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl IntoView {
-    let avatar_url = format!("{}/avatar/{}", API_BASE_URL, friend.user_id);
+    let avatar_url = avatar_url(friend.user_id);
 
     let full_name = format!("{} {}", friend.first_name.clone(), friend.last_name.clone());
     let is_online = move || friend.is_online;
@@ -27,9 +30,12 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
     let label = RwSignal::new(String::new());
     let show_message_input = RwSignal::new(false);
     let message_input = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    // Сообщение уходит через очередь отправки и может дождаться связи
+    let sent_message = RwSignal::new(None::<Uuid>);
 
     let create_label_action = Action::new_local(|(friendship_id, label): &(Uuid, String)| {
-        let (friendship_id, label) = (friendship_id.clone(), label.clone());
+        let (friendship_id, label) = (*friendship_id, label.clone());
 
         async move {
             create_friendship_label(CreateFriendshipLabelRequest {
@@ -58,42 +64,46 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
         }
     });
 
-    let send_message_action = Action::new_local(move |msg: &String| {
-        let msg = msg.clone();
-        let receiver_id = friend.user_id;
-        async move {
-            create_chat_message(CreateChatMessageRequest {
-                chat_id: None,
-                message: msg,
-                receiver_id: Some(receiver_id),
-            })
-            .await
-        }
-    });
-
-    Effect::new(move |_| {
-        if create_label_action.value().get().is_some() {
+    Effect::new(move |_| match create_label_action.value().get() {
+        Some(Ok(_)) => {
             label.set("".to_string());
+            error.set(None);
             refetch_friends.run(());
         }
+        Some(Err(e)) => {
+            error.set(Some(e.localized(&[
+                (409, "Такая метка уже есть"),
+                (404, "Друг не найден"),
+            ])))
+        }
+        None => {}
     });
 
-    Effect::new(move |_| {
-        if delete_label_action.version().get() > 0 {
-            refetch_friends.run(());
-        }
+    Effect::new(move |_| match delete_label_action.value().get() {
+        Some(Ok(_)) => refetch_friends.run(()),
+        Some(Err(e)) => error.set(Some(e.localized(&[(404, "Метка уже удалена")]))),
+        None => {}
     });
 
-    Effect::new(move |_| {
-        if delete_friendship_action.version().get() > 0 {
-            refetch_friends.run(());
-        }
+    Effect::new(move |_| match delete_friendship_action.value().get() {
+        Some(Ok(_)) => refetch_friends.run(()),
+        Some(Err(e)) => error.set(Some(friendship_error_message(&e))),
+        None => {}
     });
 
+    // Отклонённое сервером сообщение возвращаем в поле ввода
     Effect::new(move |_| {
-        if send_message_action.version().get() > 0 {
-            message_input.set(String::new());
-            show_message_input.set(false);
+        let Some(id) = sent_message.get() else {
+            return;
+        };
+        if let Some(entry) = outbox::entries().into_iter().find(|entry| entry.id == id)
+            && let OutboxState::Failed { .. } = entry.state
+        {
+            outbox::discard(id);
+            sent_message.set(None);
+            message_input.set(entry.request.message);
+            show_message_input.set(true);
+            error.set(Some(send_error_message(entry.error.as_ref())));
         }
     });
 
@@ -101,7 +111,14 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
         ev.prevent_default();
         let msg = message_input.get_untracked();
         if !msg.is_empty() {
-            send_message_action.dispatch(msg);
+            sent_message.set(Some(outbox::enqueue(CreateChatMessageRequest {
+                chat_id: None,
+                message: reply::compose(None, &msg),
+                receiver_id: Some(friend.user_id),
+            })));
+            message_input.set(String::new());
+            show_message_input.set(false);
+            error.set(None);
         }
     };
 
@@ -121,7 +138,7 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
                 </A>
                 <div>
                     <button class=style::delete_friend_button on:click=move |_| {
-                        delete_friendship_action.dispatch(friend.friendship_id.clone());
+                        delete_friendship_action.dispatch(friend.friendship_id);
                     }>
                         <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor"><path d="M14 14.252V22H4C4 17.5817 7.58172 14 12 14C12.6906 14 13.3608 14.0875 14 14.252ZM12 13C8.685 13 6 10.315 6 7C6 3.685 8.685 1 12 1C15.315 1 18 3.685 18 7C18 10.315 15.315 13 12 13ZM23 18V20H15V18H23Z"></path></svg>
                     </button>
@@ -159,6 +176,14 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
                     "Написать"
                 </button>
             </div>
+            <Show when=move || error.get().is_some()>
+                <p class=style::error_message on:click=move |_| error.set(None)>
+                    {move || error.get().unwrap_or_default()}
+                </p>
+            </Show>
+            <Show when=move || sent_message.get().and_then(outbox::state) == Some(OutboxState::Queued)>
+                <p class=style::pending_note>"Сообщение отправится, когда появится связь"</p>
+            </Show>
             <Show when= move || show_message_input.get()>
                 <form on:submit=on_message_submit class=style::message_input_area>
                     <input
@@ -174,3 +199,21 @@ pub fn FullFriendCard(friend: Friendship, refetch_friends: Callback<()>) -> impl
         </div>
     }
 }
+
+fn friendship_error_message(error: &ApiError) -> String {
+    error.localized(&[
+        (403, "Нельзя изменить эту дружбу"),
+        (404, "Вы уже не друзья"),
+        (409, "Статус дружбы уже изменён"),
+    ])
+}
+
+fn send_error_message(error: Option<&ApiError>) -> String {
+    match error {
+        Some(error) => error.localized(&[
+            (403, "Пользователь ограничил входящие сообщения"),
+            (404, "Пользователь не найден"),
+        ]),
+        None => "Не удалось отправить сообщение".to_string(),
+    }
+}
@@ -2,5 +2,8 @@ pub mod chat;
 pub mod friend_card;
 pub mod full_friend_card;
 pub mod navigation;
+pub mod profile;
+pub mod protected_route;
+pub mod rich_text;
+pub mod session_expired;
 pub mod spinner;
-pub mod profile;
\ No newline at end of file
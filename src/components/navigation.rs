@@ -1,3 +1,4 @@
+use crate::{api::outbox, drafts, unread};
 use leptos::prelude::*;
 use leptos_router::components::A;
 use stylance::import_style;
@@ -5,6 +6,10 @@ import_style!(style, "navigation.module.scss");
 
 #[component]
 pub fn Navigation() -> impl IntoView {
+    unread::track();
+    outbox::track();
+    drafts::track();
+
     view! {
         <nav class=style::nav_container>
             <div class=style::nav_left>
@@ -19,6 +24,10 @@ pub fn Navigation() -> impl IntoView {
                 </A>
                 <A href="./chats" attr:class=style::nav_link>
                     "Чаты"
+                    {move || {
+                        let total = unread::total();
+                        (total > 0).then(|| view! { <span class=style::nav_badge>{unread::badge(total)}</span> })
+                    }}
                 </A>
             </div>
             <div class=style::nav_right>
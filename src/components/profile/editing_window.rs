@@ -50,7 +50,7 @@ pub fn EditingWindow(
             new_about: Some(new_about.get_untracked()),
         };
         update_profile_action.dispatch(request);
-        if let Some(_) = selected_file.get() {
+        if selected_file.get().is_some() {
             upload_avatar_action.dispatch(());
         }
     };
@@ -58,12 +58,11 @@ pub fn EditingWindow(
         if let Some(input) = ev
             .target()
             .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            && let Some(file) = input.files().and_then(|list| list.get(0))
         {
-            if let Some(file) = input.files().and_then(|list| list.get(0)) {
-                let url = web_sys::Url::create_object_url_with_blob(&file).unwrap();
-                preview_url.set(Some(url));
-                selected_file.set(Some(file));
-            }
+            let url = web_sys::Url::create_object_url_with_blob(&file).unwrap();
+            preview_url.set(Some(url));
+            selected_file.set(Some(file));
         }
     };
 
@@ -1 +1 @@
-pub mod editing_window;
\ No newline at end of file
+pub mod editing_window;
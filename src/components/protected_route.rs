@@ -0,0 +1,44 @@
+use crate::auth::{AuthStatus, login_path, use_auth};
+use crate::config::router_base;
+use leptos::prelude::*;
+use leptos_router::NavigateOptions;
+use leptos_router::hooks::{use_location, use_navigate};
+
+/// Показывает страницу только вошедшему пользователю. Без входа отправляет
+/// на `/auth` с возвратом на текущий адрес, а при истёкшей сессии ждёт,
+/// пока пользователь закроет `SessionExpiredDialog`. При входе под другим
+/// пользователем (в том числе в другой вкладке) страница создаётся заново.
+#[component]
+pub fn ProtectedRoute(children: ChildrenFn) -> impl IntoView {
+    //SIGNALS
+    let auth = use_auth();
+    let location = use_location();
+    let navigate = use_navigate();
+    let user_id = Memo::new(move |_| {
+        (auth.status() == AuthStatus::Authenticated)
+            .then(|| auth.user_id())
+            .flatten()
+    });
+
+    //EFFECTS
+    Effect::new(move |_| {
+        if auth.status() != AuthStatus::Anonymous {
+            return;
+        }
+        let pathname = location.pathname.get_untracked();
+        let path = pathname.strip_prefix(router_base()).unwrap_or(&pathname);
+        let redirect = format!("{}{}", path, location.search.get_untracked());
+        navigate(
+            &login_path(&redirect),
+            NavigateOptions {
+                replace: true,
+                ..Default::default()
+            },
+        );
+    });
+
+    //VIEW
+    view! {
+        {move || user_id.get().map(|_| children())}
+    }
+}
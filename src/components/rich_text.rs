@@ -0,0 +1,482 @@
+use leptos::prelude::*;
+use stylance::import_style;
+
+import_style!(style, "rich_text.module.scss");
+
+/// Блок текста: абзац, цитата из строк с `> ` или блок кода между строками с ```.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Paragraph(Vec<Inline>),
+    Quote(Vec<Inline>),
+    Code(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Strike(Vec<Inline>),
+    Code(String),
+    Link(String),
+}
+
+/// Текст с лёгкой разметкой: **жирный**, *курсив* или _курсив_, ~~зачёркнутый~~,
+/// `код`, блоки кода, цитаты и ссылки http(s). Всё выводится текстовыми узлами
+/// и атрибутами Leptos, поэтому HTML из текста никогда не исполняется.
+/// Вхождения `highlight` в тексте подсвечиваются без учёта регистра.
+#[component]
+pub fn RichText(
+    #[prop(into)] text: String,
+    #[prop(optional, into)] highlight: Option<Signal<String>>,
+) -> impl IntoView {
+    let blocks = parse(&text)
+        .into_iter()
+        .map(|block| render_block(block, highlight))
+        .collect_view();
+    view! { <div class=style::rich_text>{blocks}</div> }
+}
+
+type Highlight = Option<Signal<String>>;
+
+fn render_block(block: Block, highlight: Highlight) -> AnyView {
+    match block {
+        Block::Paragraph(inlines) => {
+            view! { <p>{render_inlines(inlines, highlight)}</p> }.into_any()
+        }
+        Block::Quote(inlines) => view! {
+            <blockquote class=style::quote>{render_inlines(inlines, highlight)}</blockquote>
+        }
+        .into_any(),
+        Block::Code(code) => view! {
+            <pre class=style::code_block><code>{render_text(code, highlight)}</code></pre>
+        }
+        .into_any(),
+    }
+}
+
+fn render_inlines(inlines: Vec<Inline>, highlight: Highlight) -> Vec<AnyView> {
+    inlines
+        .into_iter()
+        .map(|inline| render_inline(inline, highlight))
+        .collect()
+}
+
+fn render_inline(inline: Inline, highlight: Highlight) -> AnyView {
+    match inline {
+        Inline::Text(text) => render_text(text, highlight),
+        Inline::Bold(inner) => {
+            view! { <strong>{render_inlines(inner, highlight)}</strong> }.into_any()
+        }
+        Inline::Italic(inner) => view! { <em>{render_inlines(inner, highlight)}</em> }.into_any(),
+        Inline::Strike(inner) => view! { <s>{render_inlines(inner, highlight)}</s> }.into_any(),
+        Inline::Code(code) => {
+            view! { <code class=style::inline_code>{render_text(code, highlight)}</code> }
+                .into_any()
+        }
+        Inline::Link(url) => {
+            let href = url.clone();
+            view! {
+                <a class=style::link href=href target="_blank" rel="noopener noreferrer">
+                    {render_text(url, highlight)}
+                </a>
+            }
+            .into_any()
+        }
+    }
+}
+
+fn render_text(text: String, highlight: Highlight) -> AnyView {
+    let Some(highlight) = highlight else {
+        return text.into_any();
+    };
+    (move || {
+        highlight.with(|query| {
+            split_matches(&text, query)
+                .into_iter()
+                .map(|(part, is_match)| {
+                    if is_match {
+                        view! { <mark class=style::highlight>{part}</mark> }.into_any()
+                    } else {
+                        part.into_any()
+                    }
+                })
+                .collect_view()
+        })
+    })
+    .into_any()
+}
+
+/// Части текста с отметкой, совпадает ли часть с `query` без учёта регистра.
+pub fn split_matches(text: &str, query: &str) -> Vec<(String, bool)> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return vec![(text.to_string(), false)];
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut parts = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let end = i + query.len();
+        if end <= chars.len()
+            && chars[i..end]
+                .iter()
+                .zip(&query)
+                .all(|(a, b)| same_letter(*a, *b))
+        {
+            if !plain.is_empty() {
+                parts.push((std::mem::take(&mut plain), false));
+            }
+            parts.push((collect(&chars[i..end]), true));
+            i = end;
+        } else {
+            plain.push(chars[i]);
+            i += 1;
+        }
+    }
+    if !plain.is_empty() {
+        parts.push((plain, false));
+    }
+    parts
+}
+
+/// Есть ли в тексте `query` без учёта регистра.
+pub fn contains_match(text: &str, query: &str) -> bool {
+    !query.is_empty()
+        && split_matches(text, query)
+            .iter()
+            .any(|(_, is_match)| *is_match)
+}
+
+fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("```") {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            // ```код``` в одну строку
+            if let Some(end) = rest.find("```") {
+                blocks.push(Block::Code(rest[..end].to_string()));
+                let tail = &rest[end + 3..];
+                if !tail.trim().is_empty() {
+                    paragraph.push(tail);
+                }
+                continue;
+            }
+            // После ``` может стоять название языка, его не показываем.
+            // Незакрытый блок продолжается до конца текста.
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::Code(code.join("\n")));
+        } else if let Some(quoted) = quote_line(line) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut quote = vec![quoted];
+            while let Some(quoted) = lines.peek().and_then(|line| quote_line(line)) {
+                quote.push(quoted);
+                lines.next();
+            }
+            blocks.push(Block::Quote(parse_inline(&quote.join("\n"))));
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+fn quote_line(line: &str) -> Option<&str> {
+    if line == ">" {
+        Some("")
+    } else {
+        line.strip_prefix("> ")
+    }
+}
+
+fn flush_paragraph(blocks: &mut Vec<Block>, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(parse_inline(&paragraph.join("\n"))));
+        paragraph.clear();
+    }
+}
+
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match span_at(&chars, i) {
+            Some((inline, next)) => {
+                if !plain.is_empty() {
+                    result.push(Inline::Text(std::mem::take(&mut plain)));
+                }
+                result.push(inline);
+                i = next;
+            }
+            None => {
+                plain.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if !plain.is_empty() {
+        result.push(Inline::Text(plain));
+    }
+    result
+}
+
+/// Разметка, начинающаяся в позиции `i`, и позиция сразу после неё.
+fn span_at(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    match chars[i] {
+        '`' => {
+            let end = (i + 1..chars.len()).find(|&j| chars[j] == '`')?;
+            (end > i + 1).then(|| (Inline::Code(collect(&chars[i + 1..end])), end + 1))
+        }
+        '*' if chars.get(i + 1) == Some(&'*') => paired(chars, i, '*', Inline::Bold),
+        '~' if chars.get(i + 1) == Some(&'~') => paired(chars, i, '~', Inline::Strike),
+        '*' | '_' => italic(chars, i),
+        'h' => link(chars, i),
+        _ => None,
+    }
+}
+
+/// Двойной разделитель: `**`, `~~`.
+fn paired(
+    chars: &[char],
+    i: usize,
+    delimiter: char,
+    wrap: fn(Vec<Inline>) -> Inline,
+) -> Option<(Inline, usize)> {
+    let start = i + 2;
+    let end = (start..chars.len().saturating_sub(1))
+        .find(|&j| chars[j] == delimiter && chars[j + 1] == delimiter)?;
+    let inner = &chars[start..end];
+    if !is_tight(inner) {
+        return None;
+    }
+    Some((wrap(parse_inline(&collect(inner))), end + 2))
+}
+
+/// Одиночный `*` или `_`. Двойные `**` внутри пропускаются, чтобы не закрыть курсив
+/// вложенным жирным. `_` работает только на границе слова, иначе `snake_case` стал бы курсивом.
+fn italic(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let delimiter = chars[i];
+    let is_word = |j: Option<&char>| j.is_some_and(|c| c.is_alphanumeric());
+    if delimiter == '_' && i > 0 && is_word(chars.get(i - 1)) {
+        return None;
+    }
+    let start = i + 1;
+    let mut j = start;
+    while j < chars.len() {
+        if chars[j] == delimiter {
+            if chars.get(j + 1) == Some(&delimiter) {
+                j += 2;
+                continue;
+            }
+            if delimiter == '_' && is_word(chars.get(j + 1)) {
+                j += 1;
+                continue;
+            }
+            let inner = &chars[start..j];
+            if !is_tight(inner) {
+                return None;
+            }
+            return Some((Inline::Italic(parse_inline(&collect(inner))), j + 1));
+        }
+        j += 1;
+    }
+    None
+}
+
+/// Ссылка http(s) до пробела. Знаки препинания в конце относятся к предложению.
+fn link(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    if i > 0 && chars[i - 1].is_alphanumeric() {
+        return None;
+    }
+    let rest = &chars[i..];
+    let scheme = ["https://", "http://"]
+        .into_iter()
+        .find(|scheme| rest.iter().take(scheme.len()).copied().eq(scheme.chars()))?;
+    let mut end = (i..chars.len())
+        .find(|&j| chars[j].is_whitespace() || matches!(chars[j], '<' | '>' | '"'))
+        .unwrap_or(chars.len());
+    while end > i {
+        let last = chars[end - 1];
+        let url = &chars[i..end - 1];
+        let unbalanced_paren = last == ')'
+            && url.iter().filter(|&&c| c == '(').count()
+                <= url.iter().filter(|&&c| c == ')').count();
+        if matches!(last, '.' | ',' | ';' | ':' | '!' | '?' | '\'') || unbalanced_paren {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+    (end - i > scheme.len()).then(|| (Inline::Link(collect(&chars[i..end])), end))
+}
+
+/// Разметка не может быть пустой и начинаться или заканчиваться пробелом: `2 * 3 * 4`.
+fn is_tight(inner: &[char]) -> bool {
+    matches!((inner.first(), inner.last()), (Some(first), Some(last)) if !first.is_whitespace() && !last.is_whitespace())
+}
+
+fn collect(chars: &[char]) -> String {
+    chars.iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Inline {
+        Inline::Text(value.to_string())
+    }
+
+    #[test]
+    fn script_stays_plain_text() {
+        assert_eq!(
+            parse("<script>alert('XSS')</script>"),
+            vec![Block::Paragraph(vec![text(
+                "<script>alert('XSS')</script>"
+            )])]
+        );
+    }
+
+    #[test]
+    fn html_attribute_does_not_leak_into_link() {
+        assert_eq!(
+            parse_inline(r#"<a href="https://evil.example">x</a>"#),
+            vec![
+                text(r#"<a href=""#),
+                Inline::Link("https://evil.example".to_string()),
+                text(r#"">x</a>"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_http_links_are_linked() {
+        assert_eq!(
+            parse_inline("javascript:alert(1)"),
+            vec![text("javascript:alert(1)")]
+        );
+        assert_eq!(parse_inline("http://"), vec![text("http://")]);
+    }
+
+    #[test]
+    fn link_drops_trailing_punctuation() {
+        assert_eq!(
+            parse_inline("см. https://example.com/a_b_c. (https://example.com/x_(y))"),
+            vec![
+                text("см. "),
+                Inline::Link("https://example.com/a_b_c".to_string()),
+                text(". ("),
+                Inline::Link("https://example.com/x_(y)".to_string()),
+                text(")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_markup() {
+        assert_eq!(
+            parse_inline("**a *b* c**"),
+            vec![Inline::Bold(vec![
+                text("a "),
+                Inline::Italic(vec![text("b")]),
+                text(" c"),
+            ])]
+        );
+        assert_eq!(
+            parse_inline("*a **b** ~~c~~*"),
+            vec![Inline::Italic(vec![
+                text("a "),
+                Inline::Bold(vec![text("b")]),
+                text(" "),
+                Inline::Strike(vec![text("c")]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn markup_inside_code_is_literal() {
+        assert_eq!(
+            parse_inline("`**x** <b>`"),
+            vec![Inline::Code("**x** <b>".to_string())]
+        );
+    }
+
+    #[test]
+    fn unclosed_and_loose_delimiters_stay_text() {
+        assert_eq!(parse_inline("**bold"), vec![text("**bold")]);
+        assert_eq!(parse_inline("2 * 3 * 4"), vec![text("2 * 3 * 4")]);
+        assert_eq!(parse_inline("****"), vec![text("****")]);
+        assert_eq!(parse_inline("``"), vec![text("``")]);
+        assert_eq!(
+            parse_inline("snake_case_name"),
+            vec![text("snake_case_name")]
+        );
+    }
+
+    #[test]
+    fn underscore_italic_on_word_boundary() {
+        assert_eq!(
+            parse_inline("это _важно_!"),
+            vec![text("это "), Inline::Italic(vec![text("важно")]), text("!")]
+        );
+    }
+
+    #[test]
+    fn code_block_keeps_content_verbatim() {
+        assert_eq!(
+            parse("до\n```rust\nfn main() {}\n<script>**x**</script>\n```\nпосле"),
+            vec![
+                Block::Paragraph(vec![text("до")]),
+                Block::Code("fn main() {}\n<script>**x**</script>".to_string()),
+                Block::Paragraph(vec![text("после")]),
+            ]
+        );
+        assert_eq!(
+            parse("```незакрытый\nблок"),
+            vec![Block::Code("блок".to_string())]
+        );
+    }
+
+    #[test]
+    fn matches_ignore_case() {
+        assert_eq!(
+            split_matches("Привет, мир! ПРИВЕТ", "привет"),
+            vec![
+                ("Привет".to_string(), true),
+                (", мир! ".to_string(), false),
+                ("ПРИВЕТ".to_string(), true),
+            ]
+        );
+        assert!(!contains_match("Привет", ""));
+        assert!(!contains_match("Привет", "пока"));
+    }
+
+    #[test]
+    fn quotes_group_consecutive_lines() {
+        assert_eq!(
+            parse("> первая\n> **вторая**\nответ\n>_<"),
+            vec![
+                Block::Quote(vec![text("первая\n"), Inline::Bold(vec![text("вторая")])]),
+                Block::Paragraph(vec![text("ответ\n>_<")]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::auth::{AuthState, AuthStatus, use_auth};
+use leptos::prelude::*;
+use stylance::import_style;
+
+import_style!(style, "session_expired.module.scss");
+
+/// `setTimeout` не принимает задержки больше ~24.8 суток.
+const MAX_TIMER_MS: i64 = i32::MAX as i64;
+
+type Timer = StoredValue<Option<TimeoutHandle>, LocalStorage>;
+
+/// Ставит таймер на истечение текущей сессии. Если срок дальше `MAX_TIMER_MS`,
+/// таймер сработает раньше и просто перепланирует себя.
+fn schedule_expiry(auth: AuthState, timer: Timer) {
+    let Some(left) = auth.session_untracked().and_then(|s| s.expires_in()) else {
+        return;
+    };
+    if left <= chrono::Duration::zero() {
+        auth.expire();
+        return;
+    }
+
+    let delay = std::time::Duration::from_millis(left.num_milliseconds().min(MAX_TIMER_MS) as u64);
+    let handle = set_timeout_with_handle(move || schedule_expiry(auth, timer), delay).ok();
+    timer.try_set_value(handle);
+}
+
+#[component]
+pub fn SessionExpiredDialog() -> impl IntoView {
+    //SIGNALS
+    let auth = use_auth();
+    // Сессию при истечении уже сбросили, поэтому имя запоминаем заранее.
+    let user_name = RwSignal::new(None::<String>);
+    let timer = StoredValue::new_local(None::<TimeoutHandle>);
+
+    //EFFECTS
+    // Перепланируем при каждой смене сессии: после входа токен уже другой.
+    Effect::new(move |_| {
+        let session = auth.session();
+        if let Some(handle) = timer.try_update_value(Option::take).flatten() {
+            handle.clear();
+        }
+        let Some(session) = session else {
+            return;
+        };
+        user_name.set(session.claims.name.clone());
+        schedule_expiry(auth, timer);
+    });
+
+    on_cleanup(move || {
+        if let Some(handle) = timer.try_update_value(Option::take).flatten() {
+            handle.clear();
+        }
+    });
+
+    //EVENTS
+    // Страница за диалогом сама отправит на вход с возвратом сюда.
+    let on_login = move |_| auth.acknowledge_expired();
+
+    //VIEW
+    view! {
+        <Show when=move || auth.status() == AuthStatus::Expired>
+            <div class=style::backdrop>
+                <div class=style::content>
+                    <h2>"Сессия истекла"</h2>
+                    <p>
+                        {move || match user_name.get() {
+                            Some(name) => format!("{}, пожалуйста, войдите снова.", name),
+                            None => "Пожалуйста, войдите снова.".to_string(),
+                        }}
+                    </p>
+                    <div class=style::actions>
+                        <button on:click=on_login>"Войти"</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,113 @@
+use crate::models::chat::DEFAULT_MAX_MESSAGE_LENGTH;
+use reqwasm::http::Request;
+use serde::Deserialize;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+// Значения по умолчанию можно переопределить при сборке через переменные окружения
+// LUNKVAY_API_BASE_URL, LUNKVAY_WS_URL и LUNKVAY_ROUTER_BASE.
+const DEFAULT_API_BASE_URL: &str = "https://lunkvay.lex48949.workers.dev/api/v1";
+const DEFAULT_WS_URL: &str = "wss://lunkvay.runasp.net/ws";
+const DEFAULT_ROUTER_BASE: &str = "/lunkvay-rs";
+const CONFIG_FILE: &str = "config.json";
+
+static CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppConfig {
+    pub api_base_url: String,
+    pub ws_url: String,
+    pub router_base: String,
+    /// Наибольшая длина сообщения в символах, которую принимает сервер.
+    pub max_message_length: usize,
+}
+
+/// Содержимое `config.json`: любое поле можно опустить, тогда берётся значение сборки.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigFile {
+    api_base_url: Option<String>,
+    ws_url: Option<String>,
+    router_base: Option<String>,
+    max_message_length: Option<usize>,
+}
+
+impl AppConfig {
+    fn from_build_env() -> Self {
+        Self {
+            api_base_url: option_env!("LUNKVAY_API_BASE_URL")
+                .unwrap_or(DEFAULT_API_BASE_URL)
+                .to_string(),
+            ws_url: option_env!("LUNKVAY_WS_URL")
+                .unwrap_or(DEFAULT_WS_URL)
+                .to_string(),
+            router_base: option_env!("LUNKVAY_ROUTER_BASE")
+                .unwrap_or(DEFAULT_ROUTER_BASE)
+                .to_string(),
+            max_message_length: DEFAULT_MAX_MESSAGE_LENGTH,
+        }
+    }
+
+    fn merge(self, file: ConfigFile) -> Self {
+        Self {
+            api_base_url: trim_url(file.api_base_url.unwrap_or(self.api_base_url)),
+            ws_url: trim_url(file.ws_url.unwrap_or(self.ws_url)),
+            router_base: trim_url(file.router_base.unwrap_or(self.router_base)),
+            max_message_length: file
+                .max_message_length
+                .filter(|&length| length > 0)
+                .unwrap_or(self.max_message_length),
+        }
+    }
+}
+
+fn trim_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Адрес `config.json` относительно `document.baseURI`, то есть того места,
+/// откуда реально отдано приложение, а не базы роутера из сборки.
+fn config_file_url() -> Option<String> {
+    let base = web_sys::window()?.document()?.base_uri().ok()??;
+    web_sys::Url::new_with_base(CONFIG_FILE, &base)
+        .ok()
+        .map(|url| url.href())
+}
+
+/// Загружает `config.json`, лежащий рядом с приложением. Вызывается один раз до монтирования;
+/// если файла нет или он битый, остаются значения сборки.
+pub async fn load() {
+    let defaults = AppConfig::from_build_env();
+
+    let file = match config_file_url() {
+        Some(url) => match Request::get(&url).send().await {
+            Ok(response) if response.ok() => {
+                response.json::<ConfigFile>().await.unwrap_or_default()
+            }
+            _ => ConfigFile::default(),
+        },
+        None => ConfigFile::default(),
+    };
+
+    let _ = CONFIG.set(defaults.merge(file));
+}
+
+pub fn config() -> &'static AppConfig {
+    CONFIG.get_or_init(|| AppConfig::from_build_env().merge(ConfigFile::default()))
+}
+
+pub fn api_base_url() -> &'static str {
+    &config().api_base_url
+}
+
+pub fn router_base() -> &'static str {
+    &config().router_base
+}
+
+pub fn max_message_length() -> usize {
+    config().max_message_length
+}
+
+pub fn ws_room_url(chat_id: Uuid) -> String {
+    format!("{}?roomId={}", config().ws_url, chat_id)
+}
@@ -0,0 +1,133 @@
+use crate::auth::use_auth;
+use crate::utils::{get_current_user_id, local_storage};
+use leptos::{ev, prelude::*};
+use leptos_use::use_event_listener;
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+use uuid::Uuid;
+
+const DRAFTS_KEY: &str = "drafts";
+
+/// Сколько ждать после последнего нажатия, прежде чем записать черновик.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Черновики, которые ещё не записаны, и таймер записи.
+#[derive(Default)]
+struct Pending {
+    drafts: HashMap<Uuid, String>,
+    timer: Option<TimeoutHandle>,
+}
+
+thread_local! {
+    /// У каждого чата свой сигнал, чтобы правка черновика не задевала другие строки списка.
+    static DRAFTS: RefCell<HashMap<Uuid, ArcRwSignal<Option<String>>>> =
+        RefCell::new(HashMap::new());
+    static PENDING: RefCell<Pending> = RefCell::new(Pending::default());
+}
+
+fn draft(chat_id: Uuid) -> ArcRwSignal<Option<String>> {
+    DRAFTS.with_borrow_mut(|drafts| {
+        drafts
+            .entry(chat_id)
+            .or_insert_with(|| ArcRwSignal::new(load(chat_id)))
+            .clone()
+    })
+}
+
+/// Черновик чата. Чтение подписывает на изменения.
+pub fn get(chat_id: Uuid) -> Option<String> {
+    draft(chat_id).get()
+}
+
+pub fn get_untracked(chat_id: Uuid) -> Option<String> {
+    PENDING
+        .with_borrow(|pending| pending.drafts.get(&chat_id).cloned())
+        .or_else(|| draft(chat_id).get_untracked())
+}
+
+/// Сохраняет черновик не сразу, а когда пользователь перестанет печатать.
+/// Пустой текст удаляет черновик сразу, чтобы после отправки он не мелькал в списке.
+pub fn save(chat_id: Uuid, text: &str) {
+    if get_untracked(chat_id).as_deref().unwrap_or_default() == text {
+        return;
+    }
+    if text.trim().is_empty() {
+        PENDING.with_borrow_mut(|pending| pending.drafts.remove(&chat_id));
+        write(chat_id, None);
+        return;
+    }
+    PENDING.with_borrow_mut(|pending| {
+        pending.drafts.insert(chat_id, text.to_string());
+        if let Some(timer) = pending.timer.take() {
+            timer.clear();
+        }
+        pending.timer = set_timeout_with_handle(flush, SAVE_DELAY).ok();
+    });
+}
+
+/// Записывает отложенные черновики. Вызывается по таймеру, при закрытии
+/// чата и перед уходом со страницы.
+pub fn flush() {
+    let drafts = PENDING.with_borrow_mut(|pending| {
+        if let Some(timer) = pending.timer.take() {
+            timer.clear();
+        }
+        std::mem::take(&mut pending.drafts)
+    });
+    for (chat_id, text) in drafts {
+        write(chat_id, Some(text));
+    }
+}
+
+/// Следит за сменой пользователя и подхватывает правки из соседних вкладок.
+/// Вызывается из навигации, которая видна на всех страницах.
+pub fn track() {
+    let auth = use_auth();
+    Effect::new(move |_| {
+        auth.user_id();
+        PENDING.with_borrow_mut(|pending| pending.drafts.clear());
+        DRAFTS.with_borrow(|drafts| {
+            for (chat_id, draft) in drafts {
+                draft.set(load(*chat_id));
+            }
+        });
+    });
+
+    let _ = use_event_listener(window(), ev::storage, |event| {
+        let Some(chat_id) = event.key().as_deref().and_then(chat_id_of) else {
+            return;
+        };
+        if let Some(draft) = DRAFTS.with_borrow(|drafts| drafts.get(&chat_id).cloned()) {
+            draft.set(event.new_value());
+        }
+    });
+    let _ = use_event_listener(window(), ev::pagehide, |_| flush());
+}
+
+fn write(chat_id: Uuid, text: Option<String>) {
+    if let (Some(storage), Some(key)) = (local_storage(), storage_key(chat_id)) {
+        let _ = match &text {
+            Some(text) => storage.set_item(&key, text),
+            None => storage.remove_item(&key),
+        };
+    }
+    let draft = draft(chat_id);
+    if draft.with_untracked(|draft| *draft != text) {
+        draft.set(text);
+    }
+}
+
+fn load(chat_id: Uuid) -> Option<String> {
+    storage_key(chat_id).and_then(|key| local_storage()?.get_item(&key).ok()?)
+}
+
+/// Черновики у каждого пользователя свои, каждый чат в отдельном ключе.
+fn storage_key(chat_id: Uuid) -> Option<String> {
+    get_current_user_id().map(|user_id| format!("{}:{}:{}", DRAFTS_KEY, user_id, chat_id))
+}
+
+/// Чат, к черновику которого относится ключ текущего пользователя.
+fn chat_id_of(key: &str) -> Option<Uuid> {
+    let user_id = get_current_user_id()?;
+    let chat_id = key.strip_prefix(&format!("{}:{}:", DRAFTS_KEY, user_id))?;
+    Uuid::parse_str(chat_id).ok()
+}
@@ -1,11 +1,22 @@
 mod api;
 mod app;
+mod auth;
 mod components;
+mod config;
+mod drafts;
 mod models;
 mod pages;
+mod sync;
+mod unread;
 mod utils;
 
 fn main() {
     console_error_panic_hook::set_once();
-    leptos::mount::mount_to_body(app::App)
+    wasm_bindgen_futures::spawn_local(async {
+        config::load().await;
+        #[cfg(any(feature = "mock", debug_assertions))]
+        api::mock::install_if_requested();
+        sync::listen();
+        leptos::mount::mount_to_body(app::App)
+    });
 }
@@ -3,6 +3,11 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use uuid::Uuid;
 
+/// Предел длины сообщения в символах по умолчанию. API_FRONTEND.md его не задаёт,
+/// поэтому настоящий предел сервера указывается в `maxMessageLength` в `config.json`.
+/// Мок ограничивает сообщения этим значением.
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 4096;
+
 #[derive(Clone, Debug, PartialEq, Deserialize_repr, Serialize_repr, Default)]
 #[serde(rename_all = "camelCase")]
 #[repr(u8)]
@@ -16,7 +21,7 @@ pub enum SystemMessageType {
     ChatUpdated = 5,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize_repr, Serialize_repr, Default, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr, Default, Copy)]
 #[repr(u8)]
 pub enum ChatMemberRole {
     #[default]
@@ -75,6 +80,15 @@ pub struct Chat {
     pub member_count: i32,
 }
 
+impl Chat {
+    /// Время последней активности: последнее сообщение или создание чата.
+    pub fn last_activity(&self) -> NaiveDateTime {
+        self.last_message
+            .as_ref()
+            .map_or(self.created_at, |message| message.created_at)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub enum WsMessageType {
@@ -86,6 +100,10 @@ pub enum WsMessageType {
     MemberDeleted,
     ChatUpdated,
     ChatDeleted,
+    /// Не приходит с сервера: `realtime` сообщает, что соединение комнаты
+    /// восстановилось после разрыва и её состояние нужно перезапросить.
+    #[serde(skip)]
+    Resync,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -99,6 +117,10 @@ pub struct PinnedMessageData {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WsMessage {
+    /// Комната события. Сервер его не присылает: клиент проставляет комнату
+    /// соединения `/ws?roomId=`, из которого пришло событие.
+    #[serde(skip)]
+    pub room_id: Option<Uuid>,
     pub r#type: WsMessageType,
     pub data: serde_json::Value,
 }
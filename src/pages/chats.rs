@@ -1,22 +1,148 @@
-use crate::api::chat::get_all_chats;
+use crate::api::{
+    cancel::LatestRequest,
+    chat::{get_all_chats, invalidate_chats},
+    chat_members::removed_from,
+    realtime::RoomSubscriptions,
+};
 use crate::components::chat::create_chat_window::CreateChatWindow;
 use crate::components::chat::messages::Messages;
+use crate::components::chat::reply;
 use crate::components::spinner::Spinner;
-use crate::models::chat::Chat;
-use crate::utils::{API_BASE_URL, get_current_user_id};
-use leptos::prelude::*;
+use crate::models::chat::{Chat, ChatMessage, WsMessage, WsMessageType};
+use crate::sync::{Topic, revision};
+use crate::utils::chat_image_url;
+use crate::{drafts, unread};
+use leptos::{html::Div, prelude::*};
+use std::collections::HashMap;
 use stylance::import_style;
+use uuid::Uuid;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlDivElement};
 
 import_style!(style, "chats.module.scss");
 
+/// Сколько длится переезд строки на новое место, мс.
+const MOVE_DURATION_MS: f64 = 300.0;
+
 #[component]
 pub fn ChatsPage() -> impl IntoView {
     //RESOURCES
-    let chats = LocalResource::new(async move || get_all_chats().await.unwrap_or_default());
+    let chats_request = LatestRequest::new();
+    let chats = LocalResource::new(move || {
+        // Новый чат могли создать в другой вкладке.
+        revision(Topic::Chats);
+        let abort = chats_request.next();
+        async move { get_all_chats(abort).await.unwrap_or_default() }
+    });
     //SIGNALS
+    let chat_list = RwSignal::new(Vec::<Chat>::new());
     let (selected_chat, set_chat) = signal(None::<Chat>);
     let (show_create_chat_window, set_show_create_chat_window) = signal(false);
     let avatar_count = RwSignal::new(0);
+    let notice = RwSignal::new(None::<String>);
+    // Анимируем только элементы, появившиеся после первой отрисовки списка
+    let animate_items = RwSignal::new(false);
+    let rooms = RoomSubscriptions::background();
+    let list_ref = NodeRef::<Div>::new();
+
+    //EFFECTS
+    Effect::new(move |_| {
+        if let Some(mut loaded) = chats.get() {
+            sort_by_activity(&mut loaded);
+            update_list(chat_list, list_ref, move |list| *list = loaded);
+            request_animation_frame(move || animate_items.set(true));
+        }
+    });
+
+    // Список слушает все комнаты пользователя, а не только открытый чат.
+    // Новые чаты находит периодический перезапрос списка в `unread::track`
+    Effect::new(move |_| {
+        let ids = chat_list.with(|list| list.iter().map(|chat| chat.id).collect::<Vec<_>>());
+        rooms.set(ids, move |msg: WsMessage| match msg.r#type {
+            WsMessageType::ReceiveMessage => {
+                if let Some(room_id) = msg.room_id
+                    && let Ok(message) = serde_json::from_value::<ChatMessage>(msg.data)
+                {
+                    update_list(chat_list, list_ref, |list| {
+                        if let Some(chat) = list.iter_mut().find(|c| c.id == room_id) {
+                            chat.last_message = Some(message);
+                        }
+                        sort_by_activity(list);
+                    });
+                }
+            }
+            WsMessageType::MessageUpdated => {
+                if let Some(room_id) = msg.room_id
+                    && let Ok(message) = serde_json::from_value::<ChatMessage>(msg.data)
+                {
+                    chat_list.update(|list| {
+                        if let Some(last) = list
+                            .iter_mut()
+                            .find(|c| c.id == room_id)
+                            .and_then(|c| c.last_message.as_mut())
+                            .filter(|last| last.id == message.id)
+                        {
+                            *last = message;
+                        }
+                    });
+                }
+            }
+            // Удалили последнее сообщение: предыдущее знает только сервер
+            WsMessageType::MessageDeleted => {
+                let deleted = serde_json::from_value::<Uuid>(msg.data).ok();
+                let is_last = chat_list.with_untracked(|list| {
+                    list.iter()
+                        .filter_map(|c| c.last_message.as_ref())
+                        .any(|last| Some(last.id) == deleted)
+                });
+                if is_last {
+                    chats.refetch();
+                }
+            }
+            WsMessageType::ChatUpdated => {
+                if let Ok(chat) = serde_json::from_value::<Chat>(msg.data) {
+                    update_list(chat_list, list_ref, |list| {
+                        if let Some(existing) = list.iter_mut().find(|c| c.id == chat.id) {
+                            *existing = chat;
+                        }
+                        sort_by_activity(list);
+                    });
+                }
+            }
+            WsMessageType::ChatDeleted => {
+                if let Some(room_id) = msg.room_id {
+                    chat_list.update(|list| list.retain(|c| c.id != room_id));
+                }
+            }
+            // Пока соединения не было, названия и последние сообщения могли смениться
+            WsMessageType::Resync => {
+                invalidate_chats();
+                chats.refetch();
+            }
+            WsMessageType::MemberDeleted => {
+                if let Some(room_id) = removed_from(&msg) {
+                    let name = chat_list.with_untracked(|list| {
+                        list.iter()
+                            .find(|c| c.id == room_id)
+                            .and_then(|c| c.name.clone())
+                    });
+                    chat_list.update(|list| list.retain(|c| c.id != room_id));
+                    if selected_chat
+                        .get_untracked()
+                        .is_some_and(|c| c.id == room_id)
+                    {
+                        set_chat.set(None);
+                        notice.set(Some(match name {
+                            Some(name) => format!("Вы больше не участник чата «{}»", name),
+                            None => "Вы больше не участник этого чата".to_string(),
+                        }));
+                    }
+                }
+            }
+            _ => {}
+        });
+    });
+
     //VIEW
     view! {
         <div class=style::container>
@@ -35,64 +161,94 @@ pub fn ChatsPage() -> impl IntoView {
                     </svg>
                     <input type="text" placeholder="Поиск..."/>
                 </div>
-                <div class=style::chat_list>
-                    <Suspense fallback=|| view! { <div class=style::spinner_container><Spinner/></div> }>
+                <div class=style::chat_list node_ref=list_ref>
+                    <Show
+                        when=move || chats.get().is_some()
+                        fallback=|| view! { <div class=style::spinner_container><Spinner/></div> }
+                    >
+                        // Строка живёт, пока живёт чат: при новом сообщении меняется её содержимое,
+                        // а сама она переезжает на новое место
                         <For
-                            each=move || chats.get().unwrap_or_default()
+                            each=move || chat_list.get()
                             key=|chat| chat.id
                             children=move |chat| {
-                                let chat_image = format!(
-                                    "{}/chat-image/{}/{}?v={}",
-                                    API_BASE_URL,
-                                    get_current_user_id().unwrap_or_default(),
-                                    chat.id,
-                                    avatar_count.get()
-                                );
+                                let chat_id = chat.id;
+                                let row = Memo::new(move |_| {
+                                    chat_list.with(|list| list.iter().find(|c| c.id == chat_id).cloned())
+                                });
+                                let chat_image = chat_image_url(chat_id, avatar_count.get());
+                                let item_class = if animate_items.get_untracked() {
+                                    format!("{} {}", style::chat_list_item, style::arrived)
+                                } else {
+                                    style::chat_list_item.to_string()
+                                };
                                 view! {
                                     <div
-                                        class=style::chat_list_item
+                                        class=item_class
+                                        data-chat-id=chat_id.to_string()
                                         style=move || {
-                                            if selected_chat.get().map_or(false, |c| c.id == chat.id) {
+                                            if selected_chat.get().is_some_and(|c| c.id == chat_id) {
                                                 "background-color: #3366CC;"
                                             } else {
                                                 ""
                                             }
                                         }
-                                        on:click=move |_| set_chat.set(Some(chat.clone()))
+                                        on:click=move |_| {
+                                            notice.set(None);
+                                            set_chat.set(row.get_untracked());
+                                        }
                                     >
-                                        {
-                                            match &chat.last_message {
-                                                Some(data) => {
-                                                    view! {
-                                                        <img class=style::avatar src=chat_image.clone() onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
-                                                        <div class=style::chat_info>
-                                                            <span class=style::chat_name>{chat.name.clone().unwrap_or_default()}</span>
-                                                            <span class=style::last_message>{data.message.clone()}</span>
-                                                        </div>
-                                                        <div class=style::chat_meta>
-                                                            <span>
-                                                                {data.created_at.format("%H:%M").to_string()}
+                                        <img class=style::avatar src=chat_image onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
+                                        {move || row.get().map(|chat| match chat.last_message {
+                                            Some(data) => {
+                                                view! {
+                                                    <div class=style::chat_info>
+                                                        <span class=style::chat_name>{chat.name.clone().unwrap_or_default()}</span>
+                                                        {
+                                                            let last_message = reply::body(&data.message).to_string();
+                                                            move || match drafts::get(chat_id) {
+                                                                Some(draft) => view! {
+                                                                    <span class=style::last_message>
+                                                                        <span class=style::draft_label>"Черновик: "</span>
+                                                                        {draft}
+                                                                    </span>
+                                                                }.into_any(),
+                                                                None => view! { <span class=style::last_message>{last_message.clone()}</span> }.into_any(),
+                                                            }
+                                                        }
+                                                    </div>
+                                                    <div class=style::chat_meta>
+                                                        <span>
+                                                            {data.created_at.format("%H:%M").to_string()}
+                                                        </span>
+                                                        {move || {
+                                                            let count = unread::count(chat_id);
+                                                            (count > 0).then(|| view! { <span class=style::unread_badge>{unread::badge(count)}</span> })
+                                                        }}
+                                                    </div>
+                                                }.into_any()
+                                            }
+                                            None => {
+                                                view!{
+                                                    <div class=style::chat_info>
+                                                        <span class=style::chat_name>{chat.name.clone().unwrap_or_default()}</span>
+                                                        {move || drafts::get(chat_id).map(|draft| view! {
+                                                            <span class=style::last_message>
+                                                                <span class=style::draft_label>"Черновик: "</span>
+                                                                {draft}
                                                             </span>
-                                                        </div>
-                                                    }.into_any()
-                                                }
-                                                None => {
-                                                    view!{
-                                                        <img class=style::avatar src=chat_image.clone() onerror="this.onerror=null;this.src='/images/chatdefault.webp';"/>
-                                                        <div class=style::chat_info>
-                                                            <span class=style::chat_name>{chat.name.clone().unwrap_or_default()}</span>
-                                                        </div>
-                                                        <div class=style::chat_meta>
-                                                        </div>
-                                                    }.into_any()
-                                                }
+                                                        })}
+                                                    </div>
+                                                    <div class=style::chat_meta>
+                                                    </div>
+                                                }.into_any()
                                             }
-                                        }
+                                        })}
                                     </div>
                                 }.into_any()
                             }
                         />
-                    </Suspense>
+                    </Show>
                 </div>
             </div>
 
@@ -101,7 +257,12 @@ pub fn ChatsPage() -> impl IntoView {
                     view! {
                         <Messages chat = chat set_chat=set_chat avatar_count=avatar_count refetch_chats=Callback::new(move |()| chats.refetch())/>
                     }.into_any()
-                }).unwrap_or_else(|| view! {<div class=style::no_chat_selected><h1>"Выберите чат чтобы начать общение"</h1></div>}.into_any())
+                }).unwrap_or_else(|| view! {
+                    <div class=style::no_chat_selected>
+                        {move || notice.get().map(|text| view! { <p class=style::notice>{text}</p> })}
+                        <h1>"Выберите чат чтобы начать общение"</h1>
+                    </div>
+                }.into_any())
                 }
             </div>
 
@@ -114,3 +275,75 @@ pub fn ChatsPage() -> impl IntoView {
         </div>
     }
 }
+
+fn sort_by_activity(chats: &mut [Chat]) {
+    chats.sort_by_key(|chat| std::cmp::Reverse(chat.last_activity()));
+}
+
+/// Меняет список так, чтобы переставленные строки плавно доехали до нового места:
+/// положения запоминаются до изменения, а после перерисовки каждая строка
+/// анимируется из старого положения в новое.
+fn update_list(
+    chat_list: RwSignal<Vec<Chat>>,
+    list_ref: NodeRef<Div>,
+    update: impl FnOnce(&mut Vec<Chat>),
+) {
+    let before = list_ref
+        .get_untracked()
+        .map(|list| row_positions(&list))
+        .unwrap_or_default();
+    chat_list.update(update);
+    if before.is_empty() {
+        return;
+    }
+    request_animation_frame(move || {
+        if let Some(list) = list_ref.try_get_untracked().flatten() {
+            animate_moves(&list, &before);
+        }
+    });
+}
+
+fn rows(list: &HtmlDivElement) -> Vec<(String, Element)> {
+    let Ok(elements) = list.query_selector_all("[data-chat-id]") else {
+        return Vec::new();
+    };
+    (0..elements.length())
+        .filter_map(|i| elements.item(i)?.dyn_into::<Element>().ok())
+        .filter_map(|element| Some((element.get_attribute("data-chat-id")?, element)))
+        .collect()
+}
+
+fn row_positions(list: &HtmlDivElement) -> HashMap<String, f64> {
+    rows(list)
+        .into_iter()
+        .map(|(id, element)| (id, element.get_bounding_client_rect().top()))
+        .collect()
+}
+
+fn animate_moves(list: &HtmlDivElement, before: &HashMap<String, f64>) {
+    for (id, element) in rows(list) {
+        let Some(old_top) = before.get(&id) else {
+            continue;
+        };
+        let shift = old_top - element.get_bounding_client_rect().top();
+        if shift.abs() < 1.0 {
+            continue;
+        }
+        let keyframes = js_sys::Array::of2(
+            &keyframe(&format!("translateY({}px)", shift)),
+            &keyframe("translateY(0)"),
+        );
+        // В web-sys `Element.animate` пока за нестабильным флагом, поэтому вызываем его сами
+        if let Ok(animate) = js_sys::Reflect::get(&element, &"animate".into())
+            && let Some(animate) = animate.dyn_ref::<js_sys::Function>()
+        {
+            let _ = animate.call2(&element, &keyframes, &MOVE_DURATION_MS.into());
+        }
+    }
+}
+
+fn keyframe(transform: &str) -> js_sys::Object {
+    let frame = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&frame, &"transform".into(), &transform.into());
+    frame
+}
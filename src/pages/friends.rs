@@ -2,7 +2,7 @@ use crate::api::friends::*;
 use crate::models::friends::FriendshipStatus;
 use crate::{
     components::{full_friend_card::FullFriendCard, spinner::Spinner},
-    utils::API_BASE_URL
+    utils::avatar_url,
 };
 use leptos::prelude::*;
 use leptos_router::components::A;
@@ -12,11 +12,8 @@ import_style!(style, "friends.module.scss");
 
 #[component]
 pub fn FriendsPage() -> impl IntoView {
-    let friends_resource = LocalResource::new(async move || {
-        get_friends( None, None)
-            .await
-            .unwrap_or_default()
-    });
+    let friends_resource =
+        LocalResource::new(async move || get_friends(None, None).await.unwrap_or_default());
 
     let incoming_requests_resource = LocalResource::new(async move || {
         get_incoming_friend_requests(None, None)
@@ -34,15 +31,12 @@ pub fn FriendsPage() -> impl IntoView {
         get_possible_friends(None, None).await.unwrap_or_default()
     });
 
-    let friendship_labels_resource = LocalResource::new(async move || {
-        get_friendship_labels().await.unwrap_or_default()
-    });
+    let friendship_labels_resource =
+        LocalResource::new(async move || get_friendship_labels().await.unwrap_or_default());
 
     let delete_friendship_label_action = Action::new_local(|label_value: &String| {
         let label_value = label_value.clone();
-        async move {
-            delete_friendship_labels_by_label_value(label_value).await
-        }
+        async move { delete_friendship_labels_by_label_value(label_value).await }
     });
 
     let accept_request_action = Action::new_local(|friendship_id: &uuid::Uuid| {
@@ -109,7 +103,7 @@ pub fn FriendsPage() -> impl IntoView {
             friendship_labels_resource.refetch();
         }
     });
-    
+
     Effect::new(move |_| {
         if cancel_request_action.version().get() > 0 {
             outgoing_requests_resource.refetch();
@@ -208,7 +202,7 @@ pub fn FriendsPage() -> impl IntoView {
                                             children=move |req| {
                                                 let sender_name = format!("{} {}", req.first_name, req.last_name);
                                                 let friendship_id = req.friendship_id;
-                                                let avatar_url = format!("{}/avatar/{}", API_BASE_URL, req.user_id);
+                                                let avatar_url = avatar_url(req.user_id);
                                                 let is_online = move || req.is_online;
                                                 view! {
                                                     <div class=style::friend_request_item>
@@ -257,7 +251,7 @@ pub fn FriendsPage() -> impl IntoView {
                                             children=move |req| {
                                                 let recipient_name = format!("{} {}", req.first_name, req.last_name);
                                                 let friendship_id = req.friendship_id;
-                                                let avatar_url = format!("{}/avatar/{}", API_BASE_URL, req.user_id);
+                                                let avatar_url = avatar_url(req.user_id);
                                                 let is_online = move || req.is_online;
                                                 view! {
                                                     <div class=style::friend_request_item>
@@ -303,7 +297,7 @@ pub fn FriendsPage() -> impl IntoView {
                                             children=move |req| {
                                                 let recipient_name = format!("{} {}", req.first_name, req.last_name);
                                                 let friendship_id = req.user_id;
-                                                let avatar_url = format!("{}/avatar/{}", API_BASE_URL, req.user_id);
+                                                let avatar_url = avatar_url(req.user_id);
                                                 let is_online = move || req.is_online;
                                                 view! {
                                                     <div class=style::friend_request_item>
@@ -1,11 +1,10 @@
+use crate::api::auth::{login, register};
+use crate::auth::use_auth;
 use crate::components::spinner::Spinner;
 use crate::models::auth::{LoginRequest, RegisterRequest};
-use crate::utils::local_storage;
 use leptos::ev;
 use leptos::prelude::*;
-use leptos_router::hooks::use_navigate;
 use stylance::import_style;
-use crate::api::auth::{register, login};
 
 import_style!(style, "login.module.scss");
 
@@ -20,15 +19,19 @@ pub fn LoginPage() -> impl IntoView {
     let email = RwSignal::new("ryan.gosling@gmail.com".to_string());
     let password = RwSignal::new("realhero".to_string());
 
-    let navigate = use_navigate();
+    let auth = use_auth();
 
     //ACTIONS
     let login_action = Action::new_local(|(email, password): &(String, String)| {
         let (email, password) = (email.clone(), password.clone());
         async move {
-            login(LoginRequest { email, password })
-                .await
-                .map_err(|e| e.to_string())
+            login(LoginRequest { email, password }).await.map_err(|e| {
+                e.localized(&[
+                    (400, "Проверьте правильность почты и пароля."),
+                    (401, "Неверная почта или пароль."),
+                    (404, "Пользователь с такой почтой не найден."),
+                ])
+            })
         }
     });
 
@@ -50,7 +53,12 @@ pub fn LoginPage() -> impl IntoView {
                     password,
                 })
                 .await
-                .map_err(|e| e.to_string())
+                .map_err(|e| {
+                    e.localized(&[(
+                        409,
+                        "Пользователь с такой почтой или никнеймом уже существует.",
+                    )])
+                })
             }
         },
     );
@@ -60,11 +68,9 @@ pub fn LoginPage() -> impl IntoView {
         if let Some(result) = login_action.value().get() {
             match result {
                 Ok(token) => {
-                    if let Some(storage) = local_storage() {
-                        let _ = storage.set_item("token", &token);
-                    }
                     error.set(None);
-                    navigate("/profile", Default::default());
+                    // Перенаправление после входа делает MainLayout.
+                    auth.sign_in(&token);
                 }
                 Err(e) => {
                     error.set(Some(e));
@@ -107,17 +113,16 @@ pub fn LoginPage() -> impl IntoView {
         let email = email.get_untracked();
         let password = password.get_untracked();
 
-        if first_name.is_empty() || last_name.is_empty() || user_name.is_empty() || email.is_empty() || password.is_empty()  {
+        if first_name.is_empty()
+            || last_name.is_empty()
+            || user_name.is_empty()
+            || email.is_empty()
+            || password.is_empty()
+        {
             error.set(Some("Все поля обязательны для заполнения.".to_string()));
             return;
         }
-        register_action.dispatch_local((
-            first_name,
-            last_name,
-            user_name,
-            email,
-            password,
-        ));
+        register_action.dispatch_local((first_name, last_name, user_name, email, password));
     };
 
     let change_form = move |ev: ev::MouseEvent| {
@@ -1,9 +1,14 @@
 use crate::{
-    api::profile::{get_current_user_profile, get_user_profile},
+    api::{
+        cancel::LatestRequest,
+        profile::{get_current_user_profile, get_user_profile},
+    },
     components::{
-        friend_card::FriendCard, profile::editing_window::EditingWindow, spinner::Spinner,
+        friend_card::FriendCard, profile::editing_window::EditingWindow, rich_text::RichText,
+        spinner::Spinner,
     },
-    utils::{API_BASE_URL, get_current_user_id},
+    sync::{Topic, revision},
+    utils::{avatar_url, get_current_user_id},
 };
 use leptos::prelude::*;
 use leptos_router::{hooks::use_params, params::Params};
@@ -25,14 +30,18 @@ pub fn ProfilePage() -> impl IntoView {
     let (avatar_count, set_avatar_count) = signal(0);
 
     //RESOURCES
+    let profile_request = LatestRequest::new();
     let profile_res = LocalResource::new(move || {
         let params = params.get();
+        // Профиль могли отредактировать в другой вкладке.
+        revision(Topic::Profile);
+        let abort = profile_request.next();
         async move {
             let params = params?;
             if let Some(user_id) = params.id {
-                get_user_profile(user_id).await
+                get_user_profile(user_id, abort).await
             } else {
-                get_current_user_profile().await
+                get_current_user_profile(abort).await
             }
         }
     });
@@ -49,7 +58,7 @@ pub fn ProfilePage() -> impl IntoView {
                                 <div class=style::user_info_card>
                                     <div class=style::avatar>
                                         <img
-                                            src=format!("{}/avatar/{}?v={}", API_BASE_URL, profile.user.id, avatar_count.get())
+                                            src=move || format!("{}?v={}-{}", avatar_url(profile.user.id), avatar_count.get(), revision(Topic::Avatar))
                                             onerror="this.onerror=null;this.src='/images/userdefault.webp';"
                                         />
                                     </div>
@@ -66,7 +75,7 @@ pub fn ProfilePage() -> impl IntoView {
                                 </div>
                                 <div class=style::section_card>
                                     <h2>"О себе"</h2>
-                                    <div>{profile.about.clone().unwrap_or_default()}</div>
+                                    <RichText text=profile.about.clone().unwrap_or_default()/>
                                 </div>
                             </div>
                             <aside class=style::sidebar>
@@ -83,7 +92,7 @@ pub fn ProfilePage() -> impl IntoView {
                                         />
                                     </div>
                                 </div>
-                                <Show when= move || get_current_user_id().map_or(false, |id| id == profile.user.id)>
+                                <Show when= move || get_current_user_id() == Some(profile.user.id)>
                                     <div class=style::actions_card>
                                         <button on:click=move |_| set_show_editing_window.set(true)>"Редактировать профиль"</button>
                                         <button class=style::secondary_button>"Настройки"</button>
@@ -95,7 +104,7 @@ pub fn ProfilePage() -> impl IntoView {
                                 <EditingWindow
                                     status=profile.status.clone().unwrap_or_default()
                                     about=profile.about.clone().unwrap_or_default()
-                                    avatar_url = format!("{}/avatar/{}", API_BASE_URL, profile.user.id)
+                                    avatar_url = avatar_url(profile.user.id)
                                     set_show_editing_window = set_show_editing_window
                                     set_avatar_count = set_avatar_count
                                     refetch_profile = Callback::new(move |()| profile_res.refetch())
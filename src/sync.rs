@@ -0,0 +1,133 @@
+use crate::api::{cache, chat::invalidate_chats};
+use crate::auth::{AuthStatus, auth_state};
+use crate::unread;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{BroadcastChannel, MessageEvent};
+
+const CHANNEL_NAME: &str = "lunkvay-sync";
+
+/// Изменение, о котором нужно узнать остальным открытым вкладкам.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SyncEvent {
+    SignedIn,
+    SignedOut,
+    SessionExpired,
+    ProfileUpdated,
+    AvatarChanged,
+    ChatsChanged,
+    #[serde(rename_all = "camelCase")]
+    ChatRead {
+        chat_id: Uuid,
+    },
+}
+
+/// Данные, которые страницы перезапрашивают, когда их изменили в другой вкладке.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topic {
+    Profile,
+    Avatar,
+    Chats,
+}
+
+struct Revisions {
+    profile: RwSignal<u32>,
+    avatar: RwSignal<u32>,
+    chats: RwSignal<u32>,
+}
+
+impl Revisions {
+    fn get(&self, topic: Topic) -> RwSignal<u32> {
+        match topic {
+            Topic::Profile => self.profile,
+            Topic::Avatar => self.avatar,
+            Topic::Chats => self.chats,
+        }
+    }
+}
+
+thread_local! {
+    static CHANNEL: Option<BroadcastChannel> = BroadcastChannel::new(CHANNEL_NAME).ok();
+    static REVISIONS: Revisions = Revisions {
+        profile: RwSignal::new(0),
+        avatar: RwSignal::new(0),
+        chats: RwSignal::new(0),
+    };
+}
+
+/// Сколько раз `topic` меняли в других вкладках или перезапрашивали. Чтение подписывает на изменения,
+/// поэтому ресурсы, которые его читают, перезапрашиваются сами.
+pub fn revision(topic: Topic) -> u32 {
+    REVISIONS.with(|revisions| revisions.get(topic).get())
+}
+
+/// Сообщает другим вкладкам об изменении. Текущая вкладка своё сообщение не получает.
+pub fn broadcast(event: SyncEvent) {
+    let Ok(message) = serde_json::to_string(&event) else {
+        return;
+    };
+    CHANNEL.with(|channel| {
+        if let Some(channel) = channel {
+            let _ = channel.post_message(&message.into());
+        }
+    });
+}
+
+/// Подписывает вкладку на изменения из других вкладок. Вызывается один раз при старте.
+pub fn listen() {
+    CHANNEL.with(|channel| {
+        let Some(channel) = channel else {
+            return;
+        };
+        let on_message = Closure::<dyn Fn(MessageEvent)>::new(|event: MessageEvent| {
+            let event = event
+                .data()
+                .as_string()
+                .and_then(|data| serde_json::from_str(&data).ok());
+            if let Some(event) = event {
+                apply(event);
+            }
+        });
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        // Канал живёт столько же, сколько вкладка.
+        on_message.forget();
+    });
+}
+
+fn apply(event: SyncEvent) {
+    let auth = auth_state();
+    match event {
+        SyncEvent::SignedIn => {
+            cache::clear();
+            auth.reload(AuthStatus::Authenticated);
+        }
+        SyncEvent::SignedOut => {
+            cache::clear();
+            auth.reload(AuthStatus::Anonymous);
+        }
+        SyncEvent::SessionExpired => {
+            cache::clear();
+            auth.reload(AuthStatus::Expired);
+        }
+        SyncEvent::ProfileUpdated => {
+            cache::invalidate("/profile");
+            bump(Topic::Profile);
+        }
+        SyncEvent::AvatarChanged => bump(Topic::Avatar),
+        SyncEvent::ChatsChanged => refresh_chats(),
+        SyncEvent::ChatRead { chat_id } => unread::clear(chat_id),
+    }
+}
+
+/// Перезапрашивает список чатов везде, где он показан в текущей вкладке.
+pub fn refresh_chats() {
+    invalidate_chats();
+    bump(Topic::Chats);
+}
+
+fn bump(topic: Topic) {
+    REVISIONS.with(|revisions| *revisions.get(topic).write() += 1);
+}
@@ -0,0 +1,320 @@
+use crate::api::{
+    cancel::LatestRequest,
+    chat::{get_all_chats, invalidate_chats},
+    chat_members::removed_from,
+    chat_messages::get_chat_messages,
+    realtime::RoomSubscriptions,
+};
+use crate::auth::use_auth;
+use crate::models::chat::{Chat, ChatMessage, SystemMessageType, WsMessage, WsMessageType};
+use crate::sync::{SyncEvent, Topic, broadcast, refresh_chats, revision};
+use crate::utils::{get_current_user_id, local_storage};
+use chrono::NaiveDateTime;
+use leptos::{prelude::*, task::spawn_local};
+use leptos_use::{use_document_visibility, use_interval_fn};
+use std::collections::HashMap;
+use uuid::Uuid;
+use web_sys::VisibilityState;
+
+const LAST_READ_KEY: &str = "last_read";
+/// Дальше этого числа непрочитанные не считаются, бейдж показывает «50+».
+const COUNT_LIMIT: u32 = 50;
+/// Сообщений за один запрос при подсчёте: обычно непрочитанных немного,
+/// и хватает одной короткой страницы.
+const COUNT_PAGE_SIZE: u32 = 10;
+/// Как часто перезапрашивать список чатов, пока вкладка на экране, в миллисекундах.
+const CHATS_REFRESH_INTERVAL: u64 = 60_000;
+
+#[derive(Clone, Copy)]
+struct Unread {
+    counts: RwSignal<HashMap<Uuid, u32>>,
+    open_chat: RwSignal<Option<Uuid>>,
+}
+
+thread_local! {
+    static UNREAD: Unread = Unread {
+        counts: RwSignal::new(HashMap::new()),
+        open_chat: RwSignal::new(None),
+    };
+}
+
+fn unread() -> Unread {
+    UNREAD.with(|unread| *unread)
+}
+
+/// Число непрочитанных сообщений в чате. Чтение подписывает на изменения.
+pub fn count(chat_id: Uuid) -> u32 {
+    unread()
+        .counts
+        .with(|counts| counts.get(&chat_id).copied().unwrap_or_default())
+}
+
+/// Непрочитанные во всех чатах пользователя.
+pub fn total() -> u32 {
+    unread().counts.with(|counts| counts.values().sum())
+}
+
+/// Текст бейджа: точное число известно только до `COUNT_LIMIT`.
+pub fn badge(count: u32) -> String {
+    if count >= COUNT_LIMIT {
+        format!("{}+", COUNT_LIMIT)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Время последнего прочитанного сообщения в чате.
+pub fn last_read(chat_id: Uuid) -> Option<NaiveDateTime> {
+    load_last_read()?.get(&chat_id).copied()
+}
+
+/// Непрочитано ли сообщение, если чат прочитан до `last_read`.
+/// Свои и системные сообщения не считаются.
+pub fn is_unread(message: &ChatMessage, last_read: Option<NaiveDateTime>) -> bool {
+    !message.is_my_message
+        && message.sender_id != get_current_user_id()
+        && message.system_message_type == SystemMessageType::None
+        && last_read.is_none_or(|read| message.created_at > read)
+}
+
+/// Отмечает чат прочитанным до `at` и сообщает об этом другим вкладкам.
+pub fn mark_read(chat_id: Uuid, at: NaiveDateTime) {
+    store_last_read(chat_id, at);
+    clear(chat_id);
+    broadcast(SyncEvent::ChatRead { chat_id });
+}
+
+/// Обнуляет счётчик чата, прочитанного в другой вкладке.
+pub fn clear(chat_id: Uuid) {
+    let counts = unread().counts;
+    if counts.with_untracked(|counts| counts.contains_key(&chat_id)) {
+        counts.update(|counts| {
+            counts.remove(&chat_id);
+        });
+    }
+}
+
+/// Чат открыт на экране: его новые сообщения сразу считаются прочитанными.
+pub fn open_chat(chat_id: Uuid) {
+    unread().open_chat.set(Some(chat_id));
+}
+
+pub fn close_chat(chat_id: Uuid) {
+    let open_chat = unread().open_chat;
+    // Соседний чат мог открыться раньше, чем закрылся этот
+    if open_chat.get_untracked() == Some(chat_id) {
+        open_chat.set(None);
+    }
+}
+
+/// Загружает чаты пользователя, пересчитывает непрочитанные и держит счётчики
+/// актуальными по событиям из комнат. Вызывается из навигации, которая видна на всех страницах.
+///
+/// О новых чатах сервер не сообщает: сокеты открыты только для уже известных комнат.
+/// Поэтому список перезапрашивается, когда вкладка снова на экране, и периодически, пока она открыта.
+pub fn track() {
+    let auth = use_auth();
+    let chats_request = LatestRequest::new();
+    let chats = LocalResource::new(move || {
+        revision(Topic::Chats);
+        let user_id = auth.user_id();
+        let abort = chats_request.next();
+        async move {
+            match user_id {
+                Some(_) => get_all_chats(abort).await.unwrap_or_default(),
+                None => Vec::new(),
+            }
+        }
+    });
+    let chat_ids = RwSignal::new(Vec::<Uuid>::new());
+    let rooms = RoomSubscriptions::background();
+    // Пользователь и последние сообщения чатов, для которых уже посчитаны непрочитанные
+    let counted = StoredValue::new((None::<Uuid>, HashMap::<Uuid, Option<Uuid>>::new()));
+
+    let visibility = use_document_visibility();
+    Effect::new(move |was_visible: Option<bool>| {
+        let is_visible = visibility.get() == VisibilityState::Visible;
+        if is_visible && was_visible == Some(false) {
+            refresh_chats();
+        }
+        is_visible
+    });
+    let _ = use_interval_fn(
+        move || {
+            if visibility.get_untracked() == VisibilityState::Visible {
+                refresh_chats();
+            }
+        },
+        CHATS_REFRESH_INTERVAL,
+    );
+
+    Effect::new(move |_| {
+        if let Some(chats) = chats.get() {
+            let user_id = get_current_user_id();
+            // Соединения достаются чатам с последней активностью
+            let mut recent: Vec<&Chat> = chats.iter().collect();
+            recent.sort_by_key(|chat| std::cmp::Reverse(chat.last_activity()));
+            let ids = recent.iter().map(|chat| chat.id).collect();
+            let last_messages: HashMap<Uuid, Option<Uuid>> = chats
+                .iter()
+                .map(|chat| (chat.id, chat.last_message.as_ref().map(|msg| msg.id)))
+                .collect();
+            // Пересчитываем только новые чаты и чаты, где появились сообщения
+            let changed: Vec<Chat> = counted.with_value(|(counted_user, counted)| {
+                chats
+                    .into_iter()
+                    .filter(|chat| {
+                        *counted_user != user_id
+                            || counted.get(&chat.id) != last_messages.get(&chat.id)
+                    })
+                    .collect()
+            });
+            let same_user = counted.with_value(|(counted_user, _)| *counted_user == user_id);
+            unread().counts.update(|counts| {
+                if same_user {
+                    counts.retain(|chat_id, _| last_messages.contains_key(chat_id));
+                } else {
+                    counts.clear();
+                }
+            });
+            chat_ids.set(ids);
+            counted.set_value((user_id, last_messages));
+            refresh(changed);
+        }
+    });
+
+    Effect::new(move |_| {
+        rooms.set(chat_ids.get(), move |msg: WsMessage| match msg.r#type {
+            WsMessageType::ReceiveMessage => {
+                if let Some(room_id) = msg.room_id
+                    && let Ok(message) = serde_json::from_value::<ChatMessage>(msg.data)
+                {
+                    // У чата сменилось последнее сообщение, список в кэше устарел
+                    invalidate_chats();
+                    record(room_id, &message);
+                }
+            }
+            WsMessageType::ChatDeleted => {
+                if let Some(room_id) = msg.room_id {
+                    clear(room_id);
+                }
+            }
+            WsMessageType::MemberDeleted => {
+                if let Some(room_id) = removed_from(&msg) {
+                    clear(room_id);
+                }
+            }
+            _ => {}
+        });
+    });
+}
+
+/// Пересчитывает непрочитанные для загруженного списка чатов.
+fn refresh(chats: Vec<Chat>) {
+    // При первом запуске отмечать нечего: всё, что было до него, считаем прочитанным
+    let Some(stored) = load_last_read() else {
+        for chat in &chats {
+            store_last_read(chat.id, chat.last_activity());
+        }
+        return;
+    };
+    let unread_chats: Vec<(Uuid, Option<NaiveDateTime>)> = chats
+        .iter()
+        .filter_map(|chat| {
+            let last_read = stored.get(&chat.id).copied();
+            let unread = chat
+                .last_message
+                .as_ref()
+                .is_some_and(|last_message| is_unread(last_message, last_read));
+            // Чат могли прочитать, пока вкладка не следила за ним
+            if !unread {
+                clear(chat.id);
+            }
+            unread.then_some((chat.id, last_read))
+        })
+        .collect();
+    if unread_chats.is_empty() {
+        return;
+    }
+    // Чаты считаем по очереди, чтобы не засыпать сервер запросами при запуске
+    spawn_local(async move {
+        for (chat_id, last_read) in unread_chats {
+            if let Some(unread_count) = count_unread(chat_id, last_read).await
+                && unread_count > 0
+            {
+                unread().counts.update(|counts| {
+                    counts.insert(chat_id, unread_count);
+                });
+            }
+        }
+    });
+}
+
+/// Листает чат от новых сообщений к старым, пока не дойдёт до прочитанного
+/// или до `COUNT_LIMIT`.
+async fn count_unread(chat_id: Uuid, last_read: Option<NaiveDateTime>) -> Option<u32> {
+    let mut unread_count = 0;
+    for page in 1..=COUNT_LIMIT.div_ceil(COUNT_PAGE_SIZE) {
+        let messages = get_chat_messages(chat_id, None, Some(page), Some(COUNT_PAGE_SIZE), None)
+            .await
+            .ok()?;
+        // Пока шёл запрос, чат могли открыть и прочитать
+        if self::last_read(chat_id) != last_read {
+            return None;
+        }
+        unread_count += messages
+            .iter()
+            .filter(|message| is_unread(message, last_read))
+            .count() as u32;
+        let reached_read = messages
+            .iter()
+            .any(|message| last_read.is_some_and(|read| message.created_at <= read));
+        if reached_read || messages.len() < COUNT_PAGE_SIZE as usize {
+            break;
+        }
+    }
+    Some(unread_count.min(COUNT_LIMIT))
+}
+
+fn record(chat_id: Uuid, message: &ChatMessage) {
+    let last_read = last_read(chat_id);
+    if unread().open_chat.get_untracked() == Some(chat_id)
+        || message.sender_id == get_current_user_id()
+    {
+        if last_read.is_none_or(|read| message.created_at > read) {
+            mark_read(chat_id, message.created_at);
+        }
+        return;
+    }
+    if is_unread(message, last_read) {
+        unread().counts.update(|counts| {
+            let count = counts.entry(chat_id).or_default();
+            *count = (*count + 1).min(COUNT_LIMIT);
+        });
+    }
+}
+
+/// Отметки прочтения хранятся локально отдельно для каждого пользователя.
+/// У сервера пока нет эндпоинта для них.
+fn storage_key() -> Option<String> {
+    get_current_user_id().map(|user_id| format!("{}:{}", LAST_READ_KEY, user_id))
+}
+
+fn load_last_read() -> Option<HashMap<Uuid, NaiveDateTime>> {
+    let value = local_storage()?.get_item(&storage_key()?).ok()??;
+    serde_json::from_str(&value).ok()
+}
+
+fn store_last_read(chat_id: Uuid, at: NaiveDateTime) {
+    let (Some(storage), Some(key)) = (local_storage(), storage_key()) else {
+        return;
+    };
+    let mut stored = load_last_read().unwrap_or_default();
+    if stored.get(&chat_id).is_some_and(|read| *read >= at) {
+        return;
+    }
+    stored.insert(chat_id, at);
+    if let Ok(value) = serde_json::to_string(&stored) {
+        let _ = storage.set_item(&key, &value);
+    }
+}
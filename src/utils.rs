@@ -1,3 +1,7 @@
+use crate::api::cache;
+use crate::auth::auth_state;
+use crate::config::api_base_url;
+use chrono::Utc;
 use jsonwebtoken::DecodingKey;
 use jsonwebtoken::Validation;
 use jsonwebtoken::decode;
@@ -6,38 +10,91 @@ use serde::Deserialize;
 use uuid::Uuid;
 use web_sys::Storage;
 
-pub const DOMAIN: &str = "lunkvay.runasp.net";
-pub const API_BASE_URL: &str = "https://lunkvay.lex48949.workers.dev/api/v1";
+const TOKEN_KEY: &str = "token";
+const TOKEN_RECEIVED_KEY: &str = "token_received_at";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct Claims {
     pub id: Uuid,
+    #[serde(default, alias = "unique_name")]
+    pub name: Option<String>,
+    pub exp: Option<i64>,
+    pub iat: Option<i64>,
 }
 
-pub fn get_current_user_id() -> Option<Uuid> {
-    let storage = local_storage()?;
-    let token = storage.get_item("token").ok().flatten()?;
+impl Claims {
+    /// Момент истечения по часам браузера. Если известно, когда токен получен,
+    /// срок жизни `exp - iat` отсчитывается от этого момента, чтобы расхождение
+    /// часов клиента и сервера не завершало сессию раньше времени.
+    pub fn expires_at(&self, received_at: Option<i64>) -> Option<i64> {
+        let exp = self.exp?;
+        match (self.iat, received_at) {
+            (Some(iat), Some(received_at)) => Some(received_at + (exp - iat)),
+            _ => Some(exp),
+        }
+    }
+}
 
+/// Claims без проверки подписи: она остаётся на сервере, клиенту нужны только поля.
+pub fn decode_claims(token: &str) -> Option<Claims> {
     let mut validation = Validation::default();
     validation.insecure_disable_signature_validation();
     validation.validate_exp = false;
     validation.validate_aud = false;
 
-    decode::<Claims>(&token, &DecodingKey::from_secret(&[]), &validation)
-        .map(|token_data| token_data.claims.id)
+    decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map(|token_data| token_data.claims)
         .ok()
 }
 
+pub fn get_token() -> Option<String> {
+    local_storage()?.get_item(TOKEN_KEY).ok().flatten()
+}
+
+pub fn store_token(token: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(TOKEN_KEY, token);
+        let _ = storage.set_item(TOKEN_RECEIVED_KEY, &Utc::now().timestamp().to_string());
+    }
+    cache::clear();
+}
+
+/// Когда браузер получил текущий токен, в секундах Unix.
+pub fn token_received_at() -> Option<i64> {
+    local_storage()?
+        .get_item(TOKEN_RECEIVED_KEY)
+        .ok()
+        .flatten()?
+        .parse()
+        .ok()
+}
+
+/// Идентификатор вошедшего пользователя из `AuthState`, без повторного разбора JWT.
+pub fn get_current_user_id() -> Option<Uuid> {
+    auth_state().user_id_untracked()
+}
+
 pub fn local_storage() -> Option<Storage> {
     window().local_storage().ok().flatten()
 }
-pub fn has_token() -> bool {
-    local_storage()
-        .and_then(|storage| storage.get_item("token").ok().flatten())
-        .is_some()
-}
 pub fn clear_token() {
     if let Some(storage) = local_storage() {
-        let _ = storage.remove_item("token");
+        let _ = storage.remove_item(TOKEN_KEY);
+        let _ = storage.remove_item(TOKEN_RECEIVED_KEY);
     }
+    cache::clear();
+}
+
+pub fn avatar_url(user_id: Uuid) -> String {
+    format!("{}/avatar/{}", api_base_url(), user_id)
+}
+
+pub fn chat_image_url(chat_id: Uuid, version: i32) -> String {
+    format!(
+        "{}/chat-image/{}/{}?v={}",
+        api_base_url(),
+        get_current_user_id().unwrap_or_default(),
+        chat_id,
+        version
+    )
 }